@@ -0,0 +1,174 @@
+//! Exporting and importing all events of a calendar as a single json file, e.g. for backups or moving a calendar to
+//! another machine
+
+use std::collections::HashSet;
+
+use crate::{
+    error::{Context, Result},
+    event::{self, Event},
+    import::{Diagnostic, ImportPreview},
+    App,
+};
+
+/// Serializes all events in [App::events_dir] into one (pretty printed) json array, sorted like [event::read_events]
+pub fn export_all(app: &App) -> Result<String> {
+    let events = event::read_events(app)?;
+
+    serde_json::to_string_pretty(&events).with_context(|| "failed to serialize events")
+}
+
+/// Recreates the events of a json array created by [export_all] in [App::events_dir] and returns the number of imported
+/// events. Events keep their original ids, unless another event with the same id is already stored, in which case
+/// they get a new id. Nothing is imported if any of the events is invalid
+pub fn import_all(app: &App, json: &str) -> Result<usize> {
+    let mut events: Vec<Event> =
+        serde_json::from_str(json).with_context(|| "failed to deserialize events")?;
+
+    for event in &events {
+        event.validate_for(app)?;
+    }
+
+    for event in &mut events {
+        event.restore(app)?;
+    }
+
+    Ok(events.len())
+}
+
+/// Checks a json array created by [export_all] like [import_all] would, without storing anything. Fails if
+/// [import_all] would fail, otherwise returns the events that would be imported, with a diagnostic for each event that
+/// would get a new id because its id is already taken
+pub fn validate_import(app: &App, json: &str) -> Result<ImportPreview> {
+    let events: Vec<Event> =
+        serde_json::from_str(json).with_context(|| "failed to deserialize events")?;
+    let mut diagnostics = Vec::new();
+    let mut kept_ids = HashSet::new();
+
+    for (index, event) in events.iter().enumerate() {
+        event.validate_for(app)?;
+
+        if event::event_exists(app, event.id()) || !kept_ids.insert(event.id()) {
+            diagnostics.push(Diagnostic {
+                index,
+                message: format!(
+                    "id {} of `{}` is already taken, the event will get a new id",
+                    event.id(),
+                    event.name
+                ),
+            });
+        }
+    }
+
+    Ok(ImportPreview {
+        events,
+        diagnostics,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+
+    #[test]
+    fn restores_backups() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        for day in 1..=3 {
+            Event::new(
+                "Event Name",
+                None,
+                Utc.ymd(2022, 4, day).and_hms(10, 0, 0),
+                None,
+                &app,
+            )
+            .unwrap()
+            .with_tags(&["work"])
+            .store(&app)
+            .unwrap();
+        }
+
+        let events = event::read_events(&app).unwrap();
+        let backup = export_all(&app).unwrap();
+
+        // Importing into an empty calendar keeps the ids
+        let restored_app = app.calendar("restored").unwrap();
+        crate::utils::create_dirs(&restored_app).unwrap();
+        assert_eq!(import_all(&restored_app, &backup).unwrap(), 3);
+        assert_eq!(event::read_events(&restored_app).unwrap(), events);
+
+        // New events don't reuse the restored ids
+        let event = Event::new(
+            "New Event",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &restored_app,
+        )
+        .unwrap();
+        assert!(events.iter().all(|restored| restored.id() != event.id()));
+
+        // Importing into the original calendar gives the events new ids instead of overwriting them
+        assert_eq!(import_all(&app, &backup).unwrap(), 3);
+        let all_events = event::read_events(&app).unwrap();
+        assert_eq!(all_events.len(), 6);
+        assert!(events.iter().all(|event| all_events.contains(event)));
+    }
+
+    #[test]
+    fn previews_imports_without_storing() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
+        let backup = export_all(&app).unwrap();
+
+        let empty_app = app.calendar("empty").unwrap();
+        crate::utils::create_dirs(&empty_app).unwrap();
+        let preview = validate_import(&empty_app, &backup).unwrap();
+        assert_eq!(preview.events, vec![event.clone()]);
+        assert!(preview.diagnostics.is_empty());
+        assert!(event::read_events(&empty_app).unwrap().is_empty());
+
+        let preview = validate_import(&app, &backup).unwrap();
+        assert_eq!(preview.diagnostics.len(), 1);
+        assert_eq!(preview.diagnostics[0].index, 0);
+        assert_eq!(event::read_events(&app).unwrap(), vec![event]);
+    }
+
+    #[test]
+    fn rejects_invalid_backups() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        assert!(import_all(&app, "not json").is_err());
+
+        let event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        let mut invalid = event.clone();
+        invalid.name = String::new();
+
+        let backup = serde_json::to_string(&[event, invalid]).unwrap();
+        assert!(import_all(&app, &backup).is_err());
+        assert!(event::read_events(&app).unwrap().is_empty());
+    }
+}
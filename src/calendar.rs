@@ -0,0 +1,350 @@
+//! An in-memory cache of all events of a calendar
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{TimeZone, Utc};
+
+use crate::{
+    error::Result,
+    event::{self, Event},
+    App,
+};
+
+/// Holds all events of a calendar in memory, so lookups don't have to read from the filesystem. Changes made through
+/// [Calendar::insert] and [Calendar::remove] are written to the filesystem right away
+pub struct Calendar<'a> {
+    app: &'a App,
+    events: HashMap<u128, Event>,
+}
+
+impl<'a> Calendar<'a> {
+    /// Reads all events from [App::events_dir] into memory
+    pub fn load(app: &'a App) -> Result<Self> {
+        let mut calendar = Self {
+            app,
+            events: HashMap::new(),
+        };
+        calendar.reload()?;
+
+        Ok(calendar)
+    }
+
+    /// Replaces the cached events with the events currently stored in [App::events_dir], picking up changes made by
+    /// other processes
+    pub fn reload(&mut self) -> Result<()> {
+        self.events = event::read_events(self.app)?
+            .into_iter()
+            .map(|event| (event.id(), event))
+            .collect();
+
+        Ok(())
+    }
+
+    /// Returns the event with the given id
+    pub fn get(&self, id: u128) -> Option<&Event> {
+        self.events.get(&id)
+    }
+
+    /// Stores the event and adds it to the calendar. Returns the previous version of the event if there was one with
+    /// the same id
+    pub fn insert(&mut self, event: Event) -> Result<Option<Event>> {
+        event.store(self.app)?;
+
+        Ok(self.events.insert(event.id(), event))
+    }
+
+    /// Deletes the event with the given id from the filesystem and the calendar. Returns the removed event, or `None`
+    /// if there was no event with that id
+    pub fn remove(&mut self, id: u128) -> Result<Option<Event>> {
+        match self.events.get(&id) {
+            Some(event) => {
+                event.delete_file(self.app)?;
+                Ok(self.events.remove(&id))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Iterates over all events of the calendar (in no particular order)
+    pub fn iter(&self) -> impl Iterator<Item = &Event> {
+        self.events.values()
+    }
+
+    /// Returns the number of events in the calendar
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns whether the calendar doesn't contain any events
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+/// The changes between two snapshots of the events of a calendar (see [diff]). All lists are sorted by id
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CalendarDiff {
+    /// Events that are only in the new snapshot
+    pub added: Vec<Event>,
+    /// Events that are only in the old snapshot
+    pub removed: Vec<Event>,
+    /// Events that are in both snapshots but differ in any field, as `(old, new)` pairs
+    pub modified: Vec<(Event, Event)>,
+}
+
+impl CalendarDiff {
+    /// Returns whether the snapshots contain the same events
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Compares two snapshots of the events of a calendar, matching events by their id
+pub fn diff(old: &[Event], new: &[Event]) -> CalendarDiff {
+    let old: BTreeMap<u128, &Event> = old.iter().map(|event| (event.id(), event)).collect();
+    let new: BTreeMap<u128, &Event> = new.iter().map(|event| (event.id(), event)).collect();
+    let mut diff = CalendarDiff::default();
+
+    for (id, &old_event) in &old {
+        match new.get(id) {
+            Some(&new_event) if new_event != old_event => {
+                diff.modified.push((old_event.clone(), new_event.clone()))
+            }
+            Some(_) => {}
+            None => diff.removed.push(old_event.clone()),
+        }
+    }
+
+    diff.added = new
+        .iter()
+        .filter(|(id, _)| !old.contains_key(id))
+        .map(|(_, &event)| event.clone())
+        .collect();
+
+    diff
+}
+
+/// What [merge] does with incoming events that conflict with stored events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Events are matched by id, a stored event is kept if an incoming event with its id differs from it
+    PreferExisting,
+    /// Events are matched by id, a stored event is overwritten if an incoming event with its id differs from it
+    PreferIncoming,
+    /// Events are matched by their contents (all fields except the id and the timestamps), incoming events are stored
+    /// unless a stored event has the same contents. Incoming events whose id is already used get a new id
+    KeepBoth,
+}
+
+/// What [merge] did with the incoming events
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    /// The number of incoming events that were stored as new events
+    pub added: usize,
+    /// The number of incoming events that weren't stored, because they are already stored or lost a conflict
+    pub skipped: usize,
+    /// The number of stored events that were overwritten with an incoming event
+    pub overwritten: usize,
+}
+
+/// Merges events from another calendar (e.g. the same calendar on another machine) into the events stored in
+/// [App::events_dir], resolving conflicts according to `policy`. Incoming events keep their ids (and timestamps) where
+/// possible. With [MergePolicy::PreferExisting] and [MergePolicy::PreferIncoming] the events are compared like [diff]
+/// does it: incoming events that are identical to the stored event with the same id are skipped
+pub fn merge(app: &App, incoming: &[Event], policy: MergePolicy) -> Result<MergeReport> {
+    let existing = event::read_events(app)?;
+    let mut report = MergeReport::default();
+
+    if policy == MergePolicy::KeepBoth {
+        let mut contents = existing
+            .iter()
+            .map(content_key)
+            .collect::<Result<HashSet<_>>>()?;
+
+        for event in incoming {
+            if contents.insert(content_key(event)?) {
+                event.clone().restore(app)?;
+                report.added += 1;
+            }
+        }
+    } else {
+        let diff = diff(&existing, incoming);
+
+        for mut event in diff.added {
+            event.restore(app)?;
+            report.added += 1;
+        }
+
+        if policy == MergePolicy::PreferIncoming {
+            for (_, event) in diff.modified {
+                event.store(app)?;
+                report.overwritten += 1;
+            }
+        }
+    }
+
+    report.skipped = incoming.len() - report.added - report.overwritten;
+
+    Ok(report)
+}
+
+/// Returns a key that is equal for events with the same contents (see [MergePolicy::KeepBoth])
+fn content_key(event: &Event) -> Result<String> {
+    let mut event = event.clone();
+    event.set_id(0);
+    event.set_timestamps(Utc.timestamp(0, 0), Utc.timestamp(0, 0));
+
+    event.to_json()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+
+    #[test]
+    fn caches_and_writes_through_changes() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let mut calendar = Calendar::load(&app).unwrap();
+        assert!(calendar.is_empty());
+
+        let event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        let id = event.id();
+
+        assert!(calendar.insert(event.clone()).unwrap().is_none());
+        assert_eq!(calendar.get(id), Some(&event));
+        assert_eq!(event::read_events(&app).unwrap(), vec![event.clone()]);
+
+        // Changes made outside of the calendar are only visible after reloading
+        event.delete_file(&app).unwrap();
+        assert_eq!(calendar.len(), 1);
+        calendar.reload().unwrap();
+        assert!(calendar.get(id).is_none());
+
+        calendar.insert(event.clone()).unwrap();
+        assert_eq!(calendar.remove(id).unwrap(), Some(event));
+        assert!(event::read_events(&app).unwrap().is_empty());
+    }
+
+    #[test]
+    fn diffs_snapshots() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = |name| {
+            Event::new(
+                name,
+                None,
+                Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+                None,
+                &app,
+            )
+            .unwrap()
+        };
+        let unchanged = event("Unchanged");
+        let removed = event("Removed");
+        let changed = event("Changed");
+        let added = event("Added");
+
+        let mut renamed = changed.clone();
+        renamed.name = "Renamed".to_string();
+
+        let old = vec![changed.clone(), unchanged.clone(), removed.clone()];
+        let new = vec![added.clone(), renamed.clone(), unchanged.clone()];
+
+        assert_eq!(
+            diff(&old, &new),
+            CalendarDiff {
+                added: vec![added],
+                removed: vec![removed],
+                modified: vec![(changed, renamed)],
+            }
+        );
+        assert!(diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn merges_calendars() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+        let other_dir = assert_fs::TempDir::new().unwrap();
+        let other = App::new(other_dir.path());
+        crate::utils::create_dirs(&other).unwrap();
+
+        let event = |name, app: &App| {
+            let event =
+                Event::new(name, None, Utc.ymd(2022, 4, 4).and_hms(10, 0, 0), None, app).unwrap();
+            event.store(app).unwrap();
+            event
+        };
+        // Both calendars hand out ids starting at the same number
+        let shared = event("Shared", &app);
+        let local = event("Local", &app);
+        let mut incoming = vec![shared.clone(), event("Remote", &other)];
+        incoming[1].set_id(local.id());
+        incoming.push(event("New", &other));
+        incoming[2].set_id(local.id() + 1);
+
+        let run = |policy| {
+            let temp_dir = assert_fs::TempDir::new().unwrap();
+            let app = App::new(temp_dir.path());
+            crate::utils::create_dirs(&app).unwrap();
+            shared.store(&app).unwrap();
+            local.store(&app).unwrap();
+
+            let report = merge(&app, &incoming, policy).unwrap();
+            let mut names: Vec<String> = event::read_events(&app)
+                .unwrap()
+                .into_iter()
+                .map(|event| event.name)
+                .collect();
+            names.sort();
+            (report, names)
+        };
+
+        let report = |added, skipped, overwritten| MergeReport {
+            added,
+            skipped,
+            overwritten,
+        };
+        assert_eq!(
+            run(MergePolicy::PreferExisting),
+            (
+                report(1, 2, 0),
+                vec!["Local".into(), "New".into(), "Shared".into()]
+            )
+        );
+        assert_eq!(
+            run(MergePolicy::PreferIncoming),
+            (
+                report(1, 1, 1),
+                vec!["New".into(), "Remote".into(), "Shared".into()]
+            )
+        );
+        assert_eq!(
+            run(MergePolicy::KeepBoth),
+            (
+                report(2, 1, 0),
+                vec![
+                    "Local".into(),
+                    "New".into(),
+                    "Remote".into(),
+                    "Shared".into()
+                ]
+            )
+        );
+    }
+}
@@ -0,0 +1,690 @@
+//! Command line interface of the program
+
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc, MAX_DATETIME, MIN_DATETIME};
+use chrono_tz::Tz;
+use clap::{Parser, Subcommand};
+
+use crate::{
+    event::{self, Event},
+    fsck,
+    output::Format,
+    parse, query, App,
+};
+
+/// Simple CLI event manager
+#[derive(Debug, Parser)]
+#[clap(author, version, about)]
+pub struct Cli {
+    /// Directory in which the data of the program is stored [default: `$XDG_DATA_HOME/evnt` or
+    /// `$HOME/.local/share/evnt`]
+    #[clap(long, global = true, env = "EVNT_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+
+    /// Never write to the data directory, e.g. to inspect a calendar that is mounted read-only. Commands that change
+    /// events fail
+    #[clap(long, global = true)]
+    pub read_only: bool,
+
+    /// Timezone times are shown in, like `Europe/Berlin`. Events are always stored in UTC [default: `display_timezone`
+    /// from the config file, otherwise the timezone of the system]
+    #[clap(long, global = true, env = "EVNT_TZ")]
+    pub tz: Option<Tz>,
+
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+impl Cli {
+    /// Returns the directory in which the data of the program is stored: `--data-dir` (or `EVNT_DATA_DIR`) if given,
+    /// otherwise `$XDG_DATA_HOME/evnt` or `$HOME/.local/share/evnt`. Empty environment variables are treated as unset
+    pub fn data_dir(&self) -> Result<PathBuf> {
+        data_dir(self.data_dir.as_deref(), |var| env::var_os(var))
+    }
+}
+
+/// The subcommands of the program
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Add a new event
+    Add {
+        /// Name of the event
+        #[clap(long)]
+        name: String,
+        /// Description of the event
+        #[clap(long)]
+        description: Option<String>,
+        /// Where the event takes place
+        #[clap(long)]
+        location: Option<String>,
+        /// A link for the event, like the join link of a video call
+        #[clap(long)]
+        url: Option<String>,
+        /// When the event starts, either in RFC 3339 (e.g. `2022-04-04T10:00:00+02:00`) or a phrase like `tomorrow 3pm`
+        /// or `next monday at 9:30` (in the timezone from the config file)
+        #[clap(long)]
+        at: String,
+        /// When the event ends (in the same formats as `--at`)
+        #[clap(long)]
+        end: Option<String>,
+    },
+    /// List all events
+    List {
+        /// How the events are printed [default: the format from the config file, or `plain`]
+        #[clap(long, arg_enum)]
+        format: Option<Format>,
+        /// Only list events that take place (at least partly) at or after this time, including ones that started
+        /// earlier but haven't ended yet (in the same formats as `add --at`)
+        #[clap(long)]
+        since: Option<String>,
+        /// Only list events that take place (at least partly) before this time (in the same formats as `add --at`)
+        #[clap(long)]
+        until: Option<String>,
+    },
+    /// List today's events (in the timezone times are shown in, see `--tz`), marking the ones that are already over
+    Today,
+    /// Show all details of an event
+    Show {
+        /// Id of the event
+        id: u128,
+    },
+    /// Edit an event in `$VISUAL` or `$EDITOR` (as json)
+    Edit {
+        /// Id of the event
+        id: u128,
+    },
+    /// Delete an event by moving it to the trash
+    Delete {
+        /// Id of the event
+        id: u128,
+    },
+    /// Bring back an event from the trash
+    Restore {
+        /// Id of the event
+        id: u128,
+    },
+    /// Permanently delete the events in the trash
+    EmptyTrash,
+    /// Check the events directory for broken files
+    Fsck {
+        /// Rename files with mismatched ids and move unreadable files to `.corrupt`
+        #[clap(long)]
+        repair: bool,
+    },
+    /// Delete all events
+    Clear {
+        /// Confirm that all events should be deleted
+        #[clap(long)]
+        yes: bool,
+    },
+}
+
+/// Executes a subcommand
+pub fn run_command(app: &App, command: Command) -> Result<()> {
+    match command {
+        Command::Add {
+            name,
+            description,
+            location,
+            url,
+            at,
+            end,
+        } => {
+            let tz = app.config.timezone;
+            let end = end
+                .as_deref()
+                .map(|end| parse_date_time(app, end, tz))
+                .transpose()?;
+            let mut event = Event::unsaved(
+                &name,
+                description.as_deref(),
+                parse_date_time(app, &at, tz)?,
+                end,
+                app.now(),
+            )?
+            .with_timezone(tz)
+            .with_reminders(app.config.default_reminder().as_slice());
+            if let Some(location) = location {
+                event = event.with_location(&location);
+            }
+            if let Some(url) = url {
+                event = event.with_url(&url)?;
+            }
+
+            // Only take an id once the event is known to be valid
+            event.validate_for(app)?;
+            event.assign_id(app)?;
+            event.store(app)?;
+
+            println!("Added event `{}` (id: {})", event.name, event.id());
+        }
+        Command::List {
+            format,
+            since,
+            until,
+        } => {
+            let tz = app.config.timezone;
+            let since = since
+                .as_deref()
+                .map(|since| parse_date_time(app, since, tz))
+                .transpose()?;
+            let until = until
+                .as_deref()
+                .map(|until| parse_date_time(app, until, tz))
+                .transpose()?;
+            let events = list_events(app, since, until)?;
+
+            format
+                .unwrap_or(app.config.format)
+                .writer(app)
+                .write(&events, &mut io::stdout().lock())?;
+        }
+        Command::Today => {
+            let tz = app.display_timezone();
+            let now = app.now();
+            let today = now.with_timezone(&tz).date().naive_local();
+
+            print!(
+                "{}",
+                format_agenda(&query::events_on_day(app, today, tz)?, now, tz)
+            );
+        }
+        Command::Show { id } => {
+            let event = Event::load(app, id)?;
+
+            println!("Name:        {}", event.name);
+            if let Some(description) = &event.description {
+                println!("Description: {}", description);
+            }
+            if let Some(location) = &event.location {
+                println!("Location:    {}", location);
+            }
+            if let Some(url) = &event.url {
+                println!("Url:         {}", url);
+            }
+            let tz = app.display_timezone();
+            println!(
+                "Start:       {}",
+                event.date_time.with_timezone(&tz).to_rfc3339()
+            );
+            if let Some(end_time) = event.end_time {
+                println!("End:         {}", end_time.with_timezone(&tz).to_rfc3339());
+            }
+            if !event.tags.is_empty() {
+                println!("Tags:        {}", event.tags.join(", "));
+            }
+            println!("Id:          {}", event.id());
+        }
+        Command::Edit { id } => {
+            let editor = ["VISUAL", "EDITOR"]
+                .into_iter()
+                .filter_map(env::var_os)
+                .find(|editor| !editor.is_empty())
+                .unwrap_or_else(|| OsString::from("vi"));
+
+            match edit_event(app, id, |path| run_editor(&editor, path))? {
+                Some(event) => println!("Updated event `{}` (id: {})", event.name, event.id()),
+                None => println!("No changes"),
+            }
+        }
+        Command::Delete { id } => {
+            event::delete_event(app, id)?;
+
+            println!("Moved event with id {} to the trash", id);
+        }
+        Command::Restore { id } => {
+            let event = event::restore_event(app, id)?;
+
+            println!("Restored event `{}` (id: {})", event.name, event.id());
+        }
+        Command::EmptyTrash => {
+            println!("Permanently deleted {} events", event::empty_trash(app)?);
+        }
+        Command::Fsck { repair } => {
+            let report = fsck::fsck(app, repair)?;
+
+            for problem in &report.problems {
+                println!("{}", problem);
+            }
+            for (from, to) in &report.moved {
+                println!(
+                    "Moved `{}` to `{}`",
+                    from.to_string_lossy(),
+                    to.to_string_lossy()
+                );
+            }
+
+            if report.problems.is_empty() {
+                println!("No problems found");
+            }
+        }
+        Command::Clear { yes } => {
+            if !yes {
+                bail!("this deletes all events, pass `--yes` to confirm");
+            }
+
+            println!("Deleted {} events", event::clear_all(app)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Formats the events of a day for the `today` command: one line per event with the id, the local start time in `tz`
+/// (or `all day`), the name and the description. Events that are over at `now` (see [Event::is_past]) are marked with
+/// `(past)`
+fn format_agenda(events: &[Event], now: DateTime<Utc>, tz: Tz) -> String {
+    let mut agenda = String::new();
+
+    for event in events {
+        let time = if event.all_day {
+            "all day".to_string()
+        } else {
+            event
+                .date_time
+                .with_timezone(&tz)
+                .format("%H:%M")
+                .to_string()
+        };
+        agenda.push_str(&format!("{:>6}  {:<7}  {}", event.id(), time, event.name));
+
+        if let Some(description) = &event.description {
+            agenda.push_str(&format!(
+                " — {}",
+                description.lines().collect::<Vec<_>>().join(" ")
+            ));
+        }
+        if event.is_past(now) {
+            agenda.push_str("  (past)");
+        }
+        agenda.push('\n');
+    }
+
+    agenda
+}
+
+/// Determines the data directory (see [Cli::data_dir]), reading environment variables with `var`
+fn data_dir(data_dir: Option<&Path>, var: impl Fn(&str) -> Option<OsString>) -> Result<PathBuf> {
+    if let Some(data_dir) = data_dir.filter(|data_dir| !data_dir.as_os_str().is_empty()) {
+        return Ok(data_dir.to_path_buf());
+    }
+
+    let var = |name| {
+        var(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+
+    if let Some(xdg_data_home) = var("XDG_DATA_HOME") {
+        return Ok(xdg_data_home.join("evnt"));
+    }
+
+    if let Some(home) = var("HOME") {
+        return Ok(home.join(".local/share/evnt"));
+    }
+
+    bail!("failed to determine the data directory: neither `--data-dir`, `EVNT_DATA_DIR`, `XDG_DATA_HOME` nor `HOME` is set")
+}
+
+/// Parses a date and time given on the command line (see [parse::parse_datetime]), relative to the current time of
+/// [App::clock]
+fn parse_date_time(app: &App, input: &str, tz: Tz) -> Result<DateTime<Utc>> {
+    Ok(parse::parse_datetime(input, app.now(), tz)?)
+}
+
+/// Lets the user edit the event with the given id: the event is written to a temporary file in its stable json
+/// representation (see [Event::to_json]), `edit` is called with the path of the file, and the edited event is read back
+/// and stored under the same id. Returns the updated event, or `None` if the file wasn't changed. Nothing is stored if
+/// editing fails or the edited file isn't a valid event. The temporary file is always removed
+fn edit_event(
+    app: &App,
+    id: u128,
+    edit: impl FnOnce(&Path) -> Result<()>,
+) -> Result<Option<Event>> {
+    let original = Event::load(app, id)?;
+    let json = original.to_json()?;
+
+    // A random name, so other users can't take the name first. The file is removed when `temp_file` is dropped
+    let mut temp_file = tempfile::Builder::new()
+        .prefix(&format!("evnt-{}-", id))
+        .suffix(".json")
+        .tempfile()
+        .context("failed to create a temporary file")?;
+    let path = temp_file.path().to_path_buf();
+    temp_file
+        .write_all(json.as_bytes())
+        .and_then(|_| temp_file.flush())
+        .with_context(|| {
+            format!(
+                "failed to write temporary file `{}`",
+                path.to_string_lossy()
+            )
+        })?;
+
+    edit(&path)?;
+    let edited = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read temporary file `{}`", path.to_string_lossy()))?;
+    drop(temp_file);
+
+    if edited == json {
+        return Ok(None);
+    }
+
+    let mut event =
+        Event::from_json(&edited).context("the edited event is invalid, nothing was changed")?;
+
+    if event.id() != id {
+        bail!("the id of an event can't be changed, nothing was changed");
+    }
+
+    // The timestamps are managed by evnt, not by the user
+    event.set_timestamps(original.created_at(), original.updated_at());
+    event.update(app)?;
+
+    Ok(Some(event))
+}
+
+/// Opens the file at `path` in `editor` (a program optionally followed by arguments, like `code --wait`) and waits
+/// until it's closed. Fails if the editor can't be started or exits unsuccessfully
+fn run_editor(editor: &OsStr, path: &Path) -> Result<()> {
+    let editor = editor.to_string_lossy();
+    let mut words = editor.split_whitespace();
+    let program = words.next().context("no editor set")?;
+
+    let status = process::Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .with_context(|| format!("failed to start editor `{}`", editor))?;
+
+    if !status.success() {
+        bail!(
+            "editor `{}` exited with {}, nothing was changed",
+            editor,
+            status
+        );
+    }
+
+    Ok(())
+}
+
+/// Reads the events to list, skipping (and reporting) files that can't be read. Only events that intersect
+/// `[since, until)` are returned (see [event::read_events_in_range]), a missing bound doesn't limit the range
+fn list_events(
+    app: &App,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+) -> Result<Vec<Event>> {
+    let (events, failures) = event::read_events_in_range_lenient(
+        app,
+        since.unwrap_or(MIN_DATETIME),
+        until.unwrap_or(MAX_DATETIME),
+        true,
+    )?;
+
+    for (path, e) in failures {
+        eprintln!("Skipping `{}`: {}", path.to_string_lossy(), e);
+    }
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_todays_agenda() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let tz = Tz::Europe__Berlin;
+        let standup = Event::new(
+            "Standup",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(7, 0, 0),
+            Some(Utc.ymd(2022, 4, 4).and_hms(7, 15, 0)),
+            &app,
+        )
+        .unwrap();
+        let review = Event::new(
+            "Review",
+            Some("Quarterly\nnumbers"),
+            Utc.ymd(2022, 4, 4).and_hms(12, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        let holiday = Event::new(
+            "Holiday",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap()
+        .with_timezone(tz)
+        .with_all_day(true);
+        for event in [&standup, &review, &holiday] {
+            event.store(&app).unwrap();
+        }
+
+        let events =
+            query::events_on_day(&app, chrono::NaiveDate::from_ymd(2022, 4, 4), tz).unwrap();
+        assert_eq!(
+            format_agenda(&events, Utc.ymd(2022, 4, 4).and_hms(9, 0, 0), tz),
+            format!(
+                "{:>6}  09:00    Standup  (past)\n\
+                 {:>6}  all day  Holiday\n\
+                 {:>6}  14:00    Review — Quarterly numbers\n",
+                standup.id(),
+                holiday.id(),
+                review.id()
+            )
+        );
+    }
+
+    #[test]
+    fn adds_and_deletes_events() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let cli = Cli::parse_from([
+            "evnt",
+            "add",
+            "--name",
+            "Meeting",
+            "--at",
+            "2022-04-04T10:00:00+02:00",
+        ]);
+        run_command(&app, cli.command).unwrap();
+
+        let events = event::read_events(&app).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].date_time.to_rfc3339(),
+            "2022-04-04T08:00:00+00:00"
+        );
+
+        // Invalid flags don't use up an id
+        let cli = Cli::parse_from([
+            "evnt",
+            "add",
+            "--name",
+            "Lunch",
+            "--at",
+            "now",
+            "--url",
+            "not a url",
+        ]);
+        assert!(run_command(&app, cli.command).is_err());
+        let cli = Cli::parse_from(["evnt", "add", "--name", "Lunch", "--at", "now"]);
+        run_command(&app, cli.command).unwrap();
+        let events = event::read_events(&app).unwrap();
+        assert_eq!(events[1].id(), events[0].id() + 1);
+
+        for event in events {
+            let id = event.id().to_string();
+            let cli = Cli::parse_from(["evnt", "delete", &id]);
+            run_command(&app, cli.command).unwrap();
+        }
+
+        assert!(event::read_events(&app).unwrap().is_empty());
+    }
+
+    #[test]
+    fn lists_events_within_bounds() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        for day in 1..=3 {
+            let at = format!("2022-04-0{}", day);
+            let cli = Cli::parse_from(["evnt", "add", "--name", "Meeting", "--at", &at]);
+            run_command(&app, cli.command).unwrap();
+        }
+
+        let events = event::read_events(&app).unwrap();
+        let bound = |input| Some(parse_date_time(&app, input, Tz::UTC).unwrap());
+
+        assert_eq!(list_events(&app, None, None).unwrap(), events);
+        assert_eq!(
+            list_events(&app, bound("2022-04-02"), None).unwrap(),
+            events[1..]
+        );
+        assert_eq!(
+            list_events(&app, None, bound("2022-04-02")).unwrap(),
+            events[..1]
+        );
+        assert_eq!(
+            list_events(&app, bound("2022-04-02"), bound("2022-04-03")).unwrap(),
+            events[1..2]
+        );
+
+        // Events that started before `--since` but are still going on are listed too
+        let cli = Cli::parse_from([
+            "evnt",
+            "add",
+            "--name",
+            "Trip",
+            "--at",
+            "2022-04-01",
+            "--end",
+            "2022-04-05",
+        ]);
+        run_command(&app, cli.command).unwrap();
+        let listed = list_events(&app, bound("2022-04-04"), None).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].name, "Trip");
+
+        let cli = Cli::parse_from(["evnt", "list", "--since", "not a date"]);
+        assert!(run_command(&app, cli.command).is_err());
+    }
+
+    #[test]
+    fn edits_events_in_a_temporary_file() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let cli = Cli::parse_from(["evnt", "add", "--name", "Meeting", "--at", "now"]);
+        run_command(&app, cli.command).unwrap();
+        let original = event::read_events(&app).unwrap().remove(0);
+        let id = original.id();
+
+        let replace = |from: &'static str, to: &'static str| {
+            move |path: &Path| {
+                let json = fs::read_to_string(path).unwrap();
+                fs::write(path, json.replace(from, to)).unwrap();
+                Ok(())
+            }
+        };
+
+        assert_eq!(edit_event(&app, id, |_| Ok(())).unwrap(), None);
+
+        // Invalid edits and failing editors leave the event untouched
+        assert!(edit_event(&app, id, replace("Meeting", "")).is_err());
+        assert!(edit_event(&app, id, replace("{", "")).is_err());
+        let mut crashed = None;
+        assert!(edit_event(&app, id, |path| {
+            crashed = Some(path.to_path_buf());
+            bail!("editor crashed")
+        })
+        .is_err());
+        assert!(!crashed.unwrap().exists());
+        assert!(edit_event(&app, id, replace("\"id\": 0", "\"id\": 5")).is_err());
+        assert_eq!(event::read_events(&app).unwrap(), vec![original.clone()]);
+
+        let mut edited_path = None;
+        let edited = edit_event(&app, id, |path| {
+            edited_path = Some(path.to_path_buf());
+            replace("Meeting", "Standup")(path)
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(edited.name, "Standup");
+        assert_eq!(edited.created_at(), original.created_at());
+        assert_eq!(event::read_events(&app).unwrap(), vec![edited]);
+        assert!(!edited_path.unwrap().exists());
+    }
+
+    #[test]
+    fn clear_requires_confirmation() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let cli = Cli::parse_from(["evnt", "add", "--name", "Meeting", "--at", "now"]);
+        run_command(&app, cli.command).unwrap();
+
+        let cli = Cli::parse_from(["evnt", "clear"]);
+        assert!(run_command(&app, cli.command).is_err());
+        assert_eq!(event::read_events(&app).unwrap().len(), 1);
+
+        let cli = Cli::parse_from(["evnt", "clear", "--yes"]);
+        run_command(&app, cli.command).unwrap();
+        assert!(event::read_events(&app).unwrap().is_empty());
+    }
+
+    #[test]
+    fn determines_data_dir() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| OsString::from(value))
+            }
+        };
+
+        assert_eq!(
+            data_dir(Some(Path::new("/override")), env(&[("HOME", "/home/user")])).unwrap(),
+            PathBuf::from("/override")
+        );
+        assert_eq!(
+            data_dir(
+                None,
+                env(&[("XDG_DATA_HOME", "/xdg"), ("HOME", "/home/user")])
+            )
+            .unwrap(),
+            PathBuf::from("/xdg/evnt")
+        );
+        assert_eq!(
+            data_dir(None, env(&[("XDG_DATA_HOME", ""), ("HOME", "/home/user")])).unwrap(),
+            PathBuf::from("/home/user/.local/share/evnt")
+        );
+        assert!(data_dir(None, env(&[("HOME", "")])).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+//! The source of the current time, so code that depends on it can be tested with a fixed time (see [crate::App::clock])
+
+use chrono::{DateTime, Utc};
+
+/// Tells the current time
+pub trait Clock {
+    /// Returns the current time
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The clock of the system, used by default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that always returns the same time, e.g. for tests
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::{event::Event, App};
+
+    #[test]
+    fn apps_use_their_clock() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let created = Utc.ymd(2022, 4, 1).and_hms(8, 0, 0);
+        let app = App::new(temp_data_dir.path()).with_clock(FixedClock(created));
+        crate::utils::create_dirs(&app).unwrap();
+
+        let mut event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
+        assert_eq!(event.created_at(), created);
+        assert_eq!(event.updated_at(), created);
+
+        let updated = Utc.ymd(2022, 4, 2).and_hms(8, 0, 0);
+        let app = app.with_clock(FixedClock(updated));
+        assert_eq!(app.calendar("work").unwrap().now(), updated);
+        event.update(&app).unwrap();
+        assert_eq!(Event::load(&app, event.id()).unwrap().updated_at(), updated);
+        assert_eq!(event.created_at(), created);
+
+        let ics = crate::output::format_events(&app, &[event], crate::output::Format::Ics).unwrap();
+        assert!(ics.contains("DTSTAMP:20220402T080000Z\r\n"));
+    }
+}
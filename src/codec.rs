@@ -0,0 +1,215 @@
+//! The formats events are encoded in when they're written to their files (see [crate::App::codec])
+
+use crate::{
+    error::{CalendarError, Context, Result},
+    event::Event,
+};
+
+/// Turns events into the bytes stored in their files and back. Files are read with the codec of the [crate::App], and
+/// files written by the built-in codecs can always be read (see [decode]), so switching to another codec doesn't make
+/// existing files unreadable, they're converted when they're written again
+pub trait Codec {
+    /// Serializes the event
+    fn encode(&self, event: &Event) -> Result<Vec<u8>>;
+
+    /// Deserializes an event serialized with [Codec::encode]
+    fn decode(&self, bytes: &[u8]) -> Result<Event>;
+
+    /// The extension (without the dot) of the files events encoded with this codec are written to. Only files with one
+    /// of the [EXTENSIONS] are read as events, so other codecs have to use one of those
+    fn extension(&self) -> &'static str;
+}
+
+/// The extensions of the files written by the built-in codecs
+pub const EXTENSIONS: [&str; 2] = [JsonCodec::EXTENSION, BincodeCodec::EXTENSION];
+
+/// Stores events as pretty printed json, which is easy to inspect and edit by hand. Used by default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl JsonCodec {
+    /// The extension of the files written by this codec
+    pub const EXTENSION: &'static str = "json";
+}
+
+impl Codec for JsonCodec {
+    fn encode(&self, event: &Event) -> Result<Vec<u8>> {
+        serde_json::to_vec_pretty(event).with_context(|| {
+            format!(
+                "failed to serialize event `{}` (id: {})",
+                event.name,
+                event.id()
+            )
+        })
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Event> {
+        serde_json::from_slice(bytes).with_context(|| "failed to deserialize event")
+    }
+
+    fn extension(&self) -> &'static str {
+        Self::EXTENSION
+    }
+}
+
+/// Stores events in the compact binary format of bincode, which is faster to read and write than json. The encoded
+/// events start with [BincodeCodec::MAGIC] to tell them apart from json. Unlike json, fields added to events by later
+/// versions can't be filled with defaults, so files have to be read by the version that wrote them
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BincodeCodec;
+
+impl BincodeCodec {
+    /// The bytes every event encoded by this codec starts with
+    pub const MAGIC: &'static [u8] = b"EVNTBIN1";
+
+    /// The extension of the files written by this codec
+    pub const EXTENSION: &'static str = "bin";
+}
+
+impl Codec for BincodeCodec {
+    fn encode(&self, event: &Event) -> Result<Vec<u8>> {
+        let mut bytes = Self::MAGIC.to_vec();
+        bincode::serialize_into(&mut bytes, event).with_context(|| {
+            format!(
+                "failed to serialize event `{}` (id: {})",
+                event.name,
+                event.id()
+            )
+        })?;
+
+        Ok(bytes)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Event> {
+        let bytes = bytes.strip_prefix(Self::MAGIC).ok_or_else(|| {
+            CalendarError::InvalidEvent("bincode encoded events start with a header".to_string())
+        })?;
+
+        bincode::deserialize(bytes).with_context(|| "failed to deserialize event")
+    }
+
+    fn extension(&self) -> &'static str {
+        Self::EXTENSION
+    }
+}
+
+/// Deserializes an event with `codec`. If that fails and the bytes look like they were written by one of the built-in
+/// codecs (e.g. before the codec was switched), they're deserialized with that one instead (see [decode_any])
+pub fn decode(codec: &dyn Codec, bytes: &[u8]) -> Result<Event> {
+    codec.decode(bytes).or_else(|e| {
+        let builtin =
+            bytes.starts_with(BincodeCodec::MAGIC) || bytes.trim_ascii_start().starts_with(b"{");
+
+        if builtin {
+            decode_any(bytes)
+        } else {
+            Err(e)
+        }
+    })
+}
+
+/// Deserializes an event encoded by any of the built-in codecs, detecting the codec from the first bytes
+pub fn decode_any(bytes: &[u8]) -> Result<Event> {
+    if bytes.starts_with(BincodeCodec::MAGIC) {
+        BincodeCodec.decode(bytes)
+    } else {
+        JsonCodec.decode(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::{event, App};
+
+    #[test]
+    fn reads_events_written_with_other_codecs() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path()).with_codec(BincodeCodec);
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = |app: &App, name| {
+            let event = Event::new(
+                name,
+                Some("Description"),
+                Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+                None,
+                app,
+            )
+            .unwrap()
+            .with_tags(&["work"]);
+            event.store(app).unwrap();
+            event
+        };
+        let binary = event(&app, "Binary");
+        let binary_path = event::event_path(&app, binary.id());
+        assert_eq!(binary_path.extension().unwrap(), "bin");
+        assert!(std::fs::read(&binary_path)
+            .unwrap()
+            .starts_with(BincodeCodec::MAGIC));
+
+        let app = app.with_codec(JsonCodec);
+        let json = event(&app, "Json");
+
+        assert_eq!(
+            event::read_events(&app).unwrap(),
+            vec![binary.clone(), json]
+        );
+
+        // Writing the event again with the other codec replaces its old file
+        binary.store(&app).unwrap();
+        assert!(!binary_path.exists());
+        assert!(event::event_path(&app, binary.id()).is_file());
+        assert_eq!(event::read_events(&app).unwrap().len(), 2);
+        assert!(BincodeCodec.decode(b"{}").is_err());
+    }
+
+    #[test]
+    fn reads_events_written_with_custom_codecs() {
+        /// Json behind a header, which the built-in codecs can't read
+        struct HeaderCodec;
+
+        impl Codec for HeaderCodec {
+            fn encode(&self, event: &Event) -> Result<Vec<u8>> {
+                let mut bytes = b"HEADER".to_vec();
+                bytes.extend(JsonCodec.encode(event)?);
+                Ok(bytes)
+            }
+
+            fn decode(&self, bytes: &[u8]) -> Result<Event> {
+                let json = bytes
+                    .strip_prefix(b"HEADER")
+                    .ok_or_else(|| CalendarError::InvalidEvent("missing header".to_string()))?;
+                JsonCodec.decode(json)
+            }
+
+            fn extension(&self) -> &'static str {
+                JsonCodec::EXTENSION
+            }
+        }
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = |app: &App, name| {
+            let event =
+                Event::new(name, None, Utc.ymd(2022, 4, 4).and_hms(10, 0, 0), None, app).unwrap();
+            event.store(app).unwrap();
+            event
+        };
+        let json = event(&app, "Json");
+
+        let app = app.with_codec(HeaderCodec);
+        let custom = event(&app, "Custom");
+        assert!(std::fs::read(event::event_path(&app, custom.id()))
+            .unwrap()
+            .starts_with(b"HEADER"));
+
+        assert_eq!(Event::load(&app, custom.id()).unwrap(), custom);
+        assert_eq!(event::read_events(&app).unwrap(), vec![json, custom]);
+        assert!(crate::fsck::fsck(&app, false).unwrap().problems.is_empty());
+    }
+}
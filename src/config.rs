@@ -0,0 +1,126 @@
+//! Persistent preferences of the user, stored in [CONFIG_FILE] in [App::data_dir]
+
+use std::{fs, io};
+
+use chrono::Duration;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Context, Result},
+    event::Layout,
+    output::Format,
+    App,
+};
+
+/// The name of the config file in [App::data_dir]
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Preferences that apply to every invocation of the program. Keys missing from the config file fall back to their
+/// defaults
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The timezone new events are created in and dates without a timezone are interpreted in
+    pub timezone: Tz,
+    /// How many minutes before the start of new events to remind of them, `None` for no reminder
+    pub default_reminder: Option<u32>,
+    /// The format events are listed in if `--format` isn't given
+    pub format: Format,
+    /// Whether newly written event files are compressed with gzip. Compressed and uncompressed files can be mixed,
+    /// existing files are only converted when they're written again
+    pub compress: bool,
+    /// How new event files are arranged in the events directory
+    pub layout: Layout,
+    /// The first and last year (inclusive) events may start or end in, e.g. `[1970, 3000]` to catch typos in years.
+    /// `None` allows any year
+    pub year_bounds: Option<(i32, i32)>,
+    /// The timezone the CLI shows times in (unless `--tz` or `EVNT_TZ` is given), `None` for the timezone of the system
+    pub display_timezone: Option<Tz>,
+    /// The number of digits the ids in the names of new event files are padded to with leading zeros (e.g. `00042.json`
+    /// with a width of 5), so directory listings sort by id. 0 for no padding. Files written with any other width are
+    /// still found by their id, finding them takes a listing of the events directory though. They get the current
+    /// width when they're written again
+    pub id_width: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            timezone: Tz::UTC,
+            default_reminder: None,
+            format: Format::Plain,
+            compress: false,
+            layout: Layout::Flat,
+            year_bounds: None,
+            display_timezone: None,
+            id_width: 0,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config from [CONFIG_FILE] in [App::data_dir]. If there is no config file yet, one with the default
+    /// config is created
+    pub fn load(app: &App) -> Result<Self> {
+        let path = app.data_dir.join(CONFIG_FILE);
+
+        match fs::read_to_string(&path) {
+            Ok(config) => toml::from_str(&config).with_context(|| {
+                format!("failed to parse config file `{}`", path.to_string_lossy())
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound && app.read_only => Ok(Self::default()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let config = Self::default();
+                let toml =
+                    toml::to_string(&config).with_context(|| "failed to serialize config")?;
+
+                fs::write(&path, toml).with_context(|| {
+                    format!("failed to write config file `{}`", path.to_string_lossy())
+                })?;
+
+                Ok(config)
+            }
+            Err(e) => Err(e).with_context(|| {
+                format!("failed to read config file `{}`", path.to_string_lossy())
+            }),
+        }
+    }
+
+    /// Returns [Config::default_reminder] as a duration
+    pub fn default_reminder(&self) -> Option<Duration> {
+        self.default_reminder
+            .map(|minutes| Duration::minutes(minutes.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_config_with_defaults() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        // A default config file is created if there is none
+        assert_eq!(Config::load(&app).unwrap(), Config::default());
+        assert!(app.data_dir.join(CONFIG_FILE).is_file());
+        assert_eq!(Config::load(&app).unwrap(), Config::default());
+
+        fs::write(
+            app.data_dir.join(CONFIG_FILE),
+            "timezone = \"Europe/Berlin\"\ndefault_reminder = 30\nyear_bounds = [1970, 3000]\n",
+        )
+        .unwrap();
+        let config = Config::load(&app).unwrap();
+        assert_eq!(config.timezone, Tz::Europe__Berlin);
+        assert_eq!(config.default_reminder(), Some(Duration::minutes(30)));
+        assert_eq!(config.format, Format::Plain);
+        assert_eq!(config.year_bounds, Some((1970, 3000)));
+
+        fs::write(app.data_dir.join(CONFIG_FILE), "format = \"xml\"\n").unwrap();
+        assert!(Config::load(&app).is_err());
+    }
+}
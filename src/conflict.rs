@@ -0,0 +1,139 @@
+//! Functions for detecting events that overlap each other
+
+use chrono::Duration;
+
+use crate::event::Event;
+
+/// Finds all pairs of events whose time intervals intersect (see [Event::overlaps] for the exact semantics, e.g.
+/// events that only touch don't conflict).
+///
+/// Each pair is ordered by start time and the pairs are returned in the order of their first event
+pub fn find_conflicts(events: &[Event]) -> Vec<(Event, Event)> {
+    let mut sorted: Vec<&Event> = events.iter().collect();
+    sorted.sort_by_key(|event| (event.start(), event.end()));
+
+    let mut conflicts = Vec::new();
+
+    for (i, event) in sorted.iter().enumerate() {
+        // Because the events are sorted by start time we only need to look at the following events until one starts
+        // after this event is over
+        for other in sorted[i + 1..]
+            .iter()
+            .take_while(|other| other.start() < event.end() || other.start() == event.start())
+        {
+            if event.overlaps(other) {
+                conflicts.push(((*event).clone(), (*other).clone()));
+            }
+        }
+    }
+
+    conflicts
+}
+
+/// Like [find_conflicts], but events also conflict if there is less than `buffer` between the end of one and the start
+/// of the other, e.g. because there isn't enough time to travel from one to the other. Only events with an end time are
+/// considered. Events that only touch conflict unless `buffer` is zero.
+///
+/// Each pair is ordered by start time and the pairs are returned in the order of their first event
+pub fn find_conflicts_with_buffer(events: &[Event], buffer: Duration) -> Vec<(Event, Event)> {
+    let mut sorted: Vec<&Event> = events
+        .iter()
+        .filter(|event| event.end_time.is_some())
+        .collect();
+    sorted.sort_by_key(|event| (event.start(), event.end()));
+
+    let mut conflicts = Vec::new();
+
+    for (i, event) in sorted.iter().enumerate() {
+        // Events are sorted by start time, so every following event that starts too soon after this one ends conflicts
+        // with it (including the ones that overlap it)
+        for other in sorted[i + 1..]
+            .iter()
+            .take_while(|other| other.start() < event.end() + buffer)
+        {
+            conflicts.push(((*event).clone(), (*other).clone()));
+        }
+    }
+
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::*;
+    use crate::App;
+
+    #[test]
+    fn finds_overlapping_events() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let start = Utc.ymd(2022, 4, 4).and_hms(10, 0, 0);
+        let event = |name: &str, offset: i64, length: i64| {
+            let date_time = start + Duration::minutes(offset);
+            Event::new(
+                name,
+                None,
+                date_time,
+                Some(date_time + Duration::minutes(length)),
+                &app,
+            )
+            .unwrap()
+        };
+
+        let meeting = event("Meeting", 0, 60);
+        let overlapping = event("Overlapping", 30, 60);
+        let touching = event("Touching", 90, 30);
+        let point = event("Point", 100, 0);
+        let separate = event("Separate", 180, 30);
+
+        let conflicts = find_conflicts(&[
+            separate,
+            touching.clone(),
+            meeting.clone(),
+            point.clone(),
+            overlapping.clone(),
+        ]);
+
+        assert_eq!(conflicts, vec![(meeting, overlapping), (touching, point)]);
+    }
+
+    #[test]
+    fn finds_events_too_close_to_each_other() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let start = Utc.ymd(2022, 4, 4).and_hms(10, 0, 0);
+        let event = |name: &str, offset: i64, length: i64| {
+            let date_time = start + Duration::minutes(offset);
+            Event::new(
+                name,
+                None,
+                date_time,
+                Some(date_time + Duration::minutes(length)),
+                &app,
+            )
+            .unwrap()
+        };
+
+        let meeting = event("Meeting", 0, 30);
+        let close = event("Close", 35, 25);
+        let later = event("Later", 80, 30);
+        let no_end = Event::new("No end", None, start + Duration::minutes(65), None, &app).unwrap();
+        let events = [later.clone(), close.clone(), no_end, meeting.clone()];
+
+        assert_eq!(
+            find_conflicts_with_buffer(&events, Duration::minutes(15)),
+            vec![(meeting.clone(), close.clone())]
+        );
+        assert_eq!(
+            find_conflicts_with_buffer(&events, Duration::minutes(25)),
+            vec![(meeting, close.clone()), (close, later)]
+        );
+        assert!(find_conflicts_with_buffer(&events, Duration::zero()).is_empty());
+    }
+}
@@ -0,0 +1,187 @@
+//! Importing events from comma separated values, e.g. exported from a spreadsheet
+
+use crate::{
+    error::{CalendarError, Context, Result},
+    event::Event,
+    import::{Diagnostic, ImportPreview},
+    parse, App,
+};
+
+/// The columns that have to be in the header row
+const REQUIRED_COLUMNS: [&str; 2] = ["name", "date_time"];
+
+/// Imports the rows of a csv file as new events (with newly generated ids) and stores them. Returns the created
+/// events together with warnings about the rows that were skipped (see [validate_csv]).
+///
+/// The first row is a header naming the columns (in any order, ignoring case). `name` and `date_time` are required,
+/// `description`, `end_time` and `location` are imported if they exist and other columns (like the `id` column of
+/// [crate::output::Format::Csv]) are ignored. Times are parsed with [parse::parse_datetime], in the timezone from the
+/// config. Fields can be quoted to contain commas, quotes (doubled) and line breaks, empty fields are treated as
+/// missing. Rows that can't be imported (e.g. with an unparsable time) are skipped. Fails if the header is missing a
+/// required column
+pub fn import_csv(app: &App, csv: &str) -> Result<(Vec<Event>, Vec<Diagnostic>)> {
+    let ImportPreview {
+        mut events,
+        diagnostics,
+    } = validate_csv(app, csv)?;
+
+    for event in &mut events {
+        event.assign_id(app)?;
+        event.store(app)?;
+    }
+
+    Ok((events, diagnostics))
+}
+
+/// Parses a csv file like [import_csv] without storing anything. Returns the events that would be imported (without
+/// ids yet) together with diagnostics for the rows that would be skipped. The index of a diagnostic is the position of
+/// the row after the header (starting at 0)
+pub fn validate_csv(app: &App, csv: &str) -> Result<ImportPreview> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(csv.as_bytes());
+
+    let header: Vec<String> = reader
+        .headers()
+        .with_context(|| "failed to read the csv header")?
+        .iter()
+        .map(|column| column.trim().to_lowercase())
+        .collect();
+
+    if let Some(missing) = REQUIRED_COLUMNS
+        .iter()
+        .find(|column| !header.iter().any(|name| name == *column))
+    {
+        return Err(CalendarError::InvalidImport(format!(
+            "the csv header is missing the `{}` column",
+            missing
+        )));
+    }
+
+    let mut preview = ImportPreview {
+        events: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+
+    for (index, record) in reader.records().enumerate() {
+        let event = record.map_err(|e| e.to_string()).and_then(|record| {
+            let field = |column: &str| {
+                header
+                    .iter()
+                    .position(|name| name == column)
+                    .and_then(|i| record.get(i))
+                    .filter(|value| !value.trim().is_empty())
+            };
+
+            parse_row(app, field)
+        });
+
+        match event {
+            Ok(event) => preview.events.push(event),
+            Err(message) => preview.diagnostics.push(Diagnostic {
+                index,
+                message: format!("skipped: {}", message),
+            }),
+        }
+    }
+
+    Ok(preview)
+}
+
+/// Converts a row into an event, `field` returns the (non-empty) value of a column
+fn parse_row<'a>(app: &App, field: impl Fn(&str) -> Option<&'a str>) -> Result<Event, String> {
+    let tz = app.config.timezone;
+    let parse_time = |column: &str| {
+        field(column)
+            .map(|value| parse::parse_datetime(value.trim(), app.now(), tz))
+            .transpose()
+            .map_err(|e| e.to_string())
+    };
+
+    let name = field("name").ok_or_else(|| "row without name".to_string())?;
+    let date_time = parse_time("date_time")?.ok_or_else(|| "row without date_time".to_string())?;
+
+    let mut event = Event::unsaved(
+        name,
+        field("description"),
+        date_time,
+        parse_time("end_time")?,
+        app.now(),
+    )
+    .map_err(|e| e.to_string())?
+    .with_timezone(tz);
+    event.location = field("location").map(String::from);
+    event.validate_for(app).map_err(|e| e.to_string())?;
+
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::{
+        event,
+        output::{self, Format},
+    };
+
+    #[test]
+    fn imports_csv() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let csv = "Name,Date_Time,Description,Extra\n\
+                   \"Meeting, important\",2022-04-04T10:00:00Z,\"Bring \"\"notes\"\"\nand coffee\",x\n\
+                   Broken,not a time,,\n\
+                   ,2022-04-04T11:00:00Z\n\
+                   Lunch,2022-04-04 12:00\n";
+
+        let preview = validate_csv(&app, csv).unwrap();
+        assert_eq!(
+            preview.diagnostics,
+            vec![
+                Diagnostic {
+                    index: 1,
+                    message: "skipped: invalid date and time `not a time`".to_string(),
+                },
+                Diagnostic {
+                    index: 2,
+                    message: "skipped: row without name".to_string(),
+                },
+            ]
+        );
+
+        let (events, warnings) = import_csv(&app, csv).unwrap();
+        assert_eq!(warnings, preview.diagnostics);
+        assert_eq!(event::read_events(&app).unwrap(), events);
+        assert_eq!(events[0].name, "Meeting, important");
+        assert_eq!(
+            events[0].description.as_deref(),
+            Some("Bring \"notes\"\nand coffee")
+        );
+        assert_eq!(events[1].name, "Lunch");
+        assert_eq!(events[1].date_time, Utc.ymd(2022, 4, 4).and_hms(12, 0, 0));
+
+        // Files written by the csv output format can be imported again
+        let other_dir = assert_fs::TempDir::new().unwrap();
+        let other = App::new(other_dir.path());
+        crate::utils::create_dirs(&other).unwrap();
+        let mut events = events;
+        events[1].location = Some("Café, 2nd floor".to_string());
+        let (imported, warnings) = import_csv(
+            &other,
+            &output::format_events(&app, &events, Format::Csv).unwrap(),
+        )
+        .unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported[1].location, events[1].location);
+
+        assert!(matches!(
+            import_csv(&app, "name,description\nMeeting,\n"),
+            Err(CalendarError::InvalidImport(_))
+        ));
+    }
+}
@@ -0,0 +1,158 @@
+//! The error type returned by the functions of this library
+
+use std::io;
+
+use thiserror::Error;
+
+/// Result type with [CalendarError] as the default error
+pub type Result<T, E = CalendarError> = std::result::Result<T, E>;
+
+/// Describes everything that can go wrong when working with events
+#[derive(Debug, Error)]
+pub enum CalendarError {
+    /// There is no event with the given id
+    #[error("event with id {0} not found")]
+    NotFound(u128),
+
+    /// An event with the given id already exists (see [crate::event::Event::with_id])
+    #[error("event with id {0} already exists")]
+    IdTaken(u128),
+
+    /// The [crate::App] is read-only (see [crate::App::read_only]), so nothing can be written
+    #[error("the calendar is opened read-only, events can't be changed")]
+    ReadOnly,
+
+    /// Every possible event id has already been handed out
+    #[error("no event ids left")]
+    IdsExhausted,
+
+    /// There is no template with the given name (see [crate::template])
+    #[error("template `{0}` not found")]
+    TemplateNotFound(String),
+
+    /// The name of an event is invalid (e.g. empty)
+    #[error("invalid event name: {0}")]
+    InvalidName(String),
+
+    /// The event has fields that are inconsistent with each other (e.g. it ends before it starts)
+    #[error("invalid event: {0}")]
+    InvalidEvent(String),
+
+    /// The name of a calendar contains characters that aren't allowed (see [crate::App::calendar])
+    #[error("invalid calendar name `{0}`")]
+    InvalidCalendarName(String),
+
+    /// The data directory can't be used, e.g. because it's a file or can't be written to (see [crate::App::try_new])
+    #[error("invalid data directory: {0}")]
+    InvalidDataDir(String),
+
+    /// A file can't be imported as a whole, e.g. because its header is missing a required column (see
+    /// [crate::csv::import_csv])
+    #[error("invalid import: {0}")]
+    InvalidImport(String),
+
+    /// A date and time couldn't be parsed (see [crate::parse::parse_datetime])
+    #[error("invalid date and time `{0}`")]
+    InvalidDateTime(String),
+
+    /// Deleting several events at once failed partway through (see [crate::event::delete_where]). The events with the
+    /// ids in `deleted` had already been moved to the trash, the others weren't touched
+    #[error("deleting events failed after deleting the events with ids {deleted:?}: {source}")]
+    PartialDelete {
+        deleted: Vec<u128>,
+        source: Box<CalendarError>,
+    },
+
+    /// Reading from or writing to the filesystem failed
+    #[error("{context}")]
+    Io {
+        /// What was being done when the error occurred
+        context: String,
+        source: io::Error,
+    },
+
+    /// Watching the events directory for changes failed (see [crate::watch::watch])
+    #[error("{context}")]
+    Watch {
+        /// What was being done when the error occurred
+        context: String,
+        source: notify::Error,
+    },
+
+    /// An event couldn't be serialized or deserialized (e.g. because the file it's stored in is corrupt)
+    #[error("{context}")]
+    Serialization {
+        /// What was being done when the error occurred
+        context: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+/// Adds context to errors from other libraries, converting them into a [CalendarError] (similar to
+/// [anyhow::Context])
+pub(crate) trait Context<T> {
+    fn with_context<C: ToString, F: FnOnce() -> C>(self, context: F) -> Result<T>;
+}
+
+impl<T> Context<T> for io::Result<T> {
+    fn with_context<C: ToString, F: FnOnce() -> C>(self, context: F) -> Result<T> {
+        self.map_err(|source| CalendarError::Io {
+            context: context().to_string(),
+            source,
+        })
+    }
+}
+
+impl<T> Context<T> for serde_json::Result<T> {
+    fn with_context<C: ToString, F: FnOnce() -> C>(self, context: F) -> Result<T> {
+        self.map_err(|source| CalendarError::Serialization {
+            context: context().to_string(),
+            source: source.into(),
+        })
+    }
+}
+
+impl<T> Context<T> for Result<T, toml::de::Error> {
+    fn with_context<C: ToString, F: FnOnce() -> C>(self, context: F) -> Result<T> {
+        self.map_err(|source| CalendarError::Serialization {
+            context: context().to_string(),
+            source: source.into(),
+        })
+    }
+}
+
+impl<T> Context<T> for csv::Result<T> {
+    fn with_context<C: ToString, F: FnOnce() -> C>(self, context: F) -> Result<T> {
+        self.map_err(|source| CalendarError::Serialization {
+            context: context().to_string(),
+            source: source.into(),
+        })
+    }
+}
+
+impl<T> Context<T> for Result<T, toml::ser::Error> {
+    fn with_context<C: ToString, F: FnOnce() -> C>(self, context: F) -> Result<T> {
+        self.map_err(|source| CalendarError::Serialization {
+            context: context().to_string(),
+            source: source.into(),
+        })
+    }
+}
+
+impl<T> Context<T> for notify::Result<T> {
+    fn with_context<C: ToString, F: FnOnce() -> C>(self, context: F) -> Result<T> {
+        self.map_err(|source| CalendarError::Watch {
+            context: context().to_string(),
+            source,
+        })
+    }
+}
+
+impl<T> Context<T> for bincode::Result<T> {
+    fn with_context<C: ToString, F: FnOnce() -> C>(self, context: F) -> Result<T> {
+        self.map_err(|source| CalendarError::Serialization {
+            context: context().to_string(),
+            source,
+        })
+    }
+}
@@ -1,12 +1,62 @@
 //! Functions and structs for managing calendar events
 
-use std::fs;
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    fmt,
+    fs::{self, DirEntry, FileType},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
-use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use fs2::FileExt;
 use serde::{Deserialize, Serialize};
+use url::Url;
 
-use crate::App;
+use crate::{
+    codec,
+    error::{CalendarError, Context, Result},
+    recurrence::Recurrence,
+    utils, App,
+};
+
+/// The extension of the sidecar files longer notes of events are stored in (see [get_notes])
+const NOTES_EXTENSION: &str = "notes";
+
+/// The name of the file in [App::events_dir] that is locked while reading ids and writing events, so multiple
+/// processes can use the same events directory at once
+const LOCK_FILE: &str = ".lock";
+
+/// The name of the file in [App::events_dir] that stores the next id that will be handed out by [generate_id]
+const NEXT_ID_FILE: &str = ".next_id";
+
+/// The name of the directory in [App::events_dir] deleted events are moved to (see [delete_event])
+const TRASH_DIR: &str = ".trash";
+
+/// The format of the names of month directories (see [Layout::Monthly])
+const MONTH_FORMAT: &str = "%Y-%m";
+
+/// The bytes every gzip compressed file starts with, used to tell compressed event files apart from uncompressed ones
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The maximum number of characters in the name of an event
+pub const MAX_NAME_LENGTH: usize = 256;
+
+/// The first year events can be in. Years before it (0 and negative years) can't be written in the four-digit
+/// formats of RFC 3339 and iCalendar, or aren't understood by most programs
+pub const MIN_YEAR: i32 = 1;
+
+/// The last year events can be in. Later years would need more than four digits in RFC 3339 and iCalendar
+pub const MAX_YEAR: i32 = 9999;
+
+/// The color names that can be used as [Event::color] (besides hex codes)
+pub const NAMED_COLORS: [&str; 11] = [
+    "black", "blue", "brown", "gray", "green", "orange", "pink", "purple", "red", "white", "yellow",
+];
 
 /// An event that can be added to the calendar
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -15,247 +65,3632 @@ pub struct Event {
     pub name: String,
     /// Optional description for the event
     pub description: Option<String>,
+    /// Where the event takes place (e.g. a room, an address or a link to a video call)
+    #[serde(default)]
+    pub location: Option<String>,
+    /// Who is invited to the event (email addresses or names)
+    #[serde(default)]
+    pub attendees: Vec<String>,
+
+    /// The time at which the event occurs (stored in UTC, use [Event::local_time] for the time in [Event::tz])
+    pub date_time: DateTime<Utc>,
+    /// The time at which the event ends, `None` if the event is instantaneous. Can't be before [Event::date_time]
+    pub end_time: Option<DateTime<Utc>>,
+    /// Whether the event lasts the whole day (like a birthday or holiday) instead of happening at a specific time.
+    /// All-day events cover the whole (local) day of [Event::date_time] up to the end of the day of [Event::end_time]
+    /// (or just the one day if there is no end time)
+    #[serde(default)]
+    pub all_day: bool,
+
+    /// The timezone the event was created in. Times are stored in UTC, but the timezone is needed to get the local
+    /// (wall-clock) time back, e.g. for recurring events across daylight saving time changes
+    #[serde(default = "default_tz")]
+    pub tz: Tz,
+
+    /// How the event repeats, `None` if it only occurs once
+    pub recurrence: Option<Recurrence>,
+
+    /// Tags (categories) of the event, like `work` or `health`
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// How important the event is
+    #[serde(default)]
+    pub priority: Priority,
+    /// Whether the event is still scheduled, took place or was cancelled
+    #[serde(default)]
+    pub status: EventStatus,
+    /// The color the event is displayed in by frontends, either a hex code like `#1E90FF` or one of [NAMED_COLORS]
+    #[serde(default)]
+    pub color: Option<String>,
+    /// A link for the event, like the join link of a video call. Has to be a valid absolute URL (see [validate_url])
+    #[serde(default)]
+    pub url: Option<String>,
+
+    /// When to be reminded of the event, as offsets before the event starts (see [Event::start]). A zero offset
+    /// reminds right when the event starts, negative offsets aren't allowed. Stored in whole seconds
+    #[serde(default, with = "duration_seconds")]
+    pub reminders: Vec<Duration>,
+
+    /// When the event was created. Events stored before this was recorded use the Unix epoch
+    #[serde(default = "epoch")]
+    created_at: DateTime<Utc>,
+    /// When the event was last changed with [Event::update] (the same as [Event::created_at] until then)
+    #[serde(default = "epoch")]
+    updated_at: DateTime<Utc>,
+
+    /// Unique id for the event (unique within its calendar, see [App::calendar]). This is necessary because different
+    /// events can have the same name. Also acts as the filename for the serialized event
+    id: u128,
+}
+
+/// How important an event is. Priorities are ordered from [Priority::Low] to [Priority::High]
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// The state of an event. Completed and cancelled events are kept (unlike deleted ones), so there is a record of them
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum EventStatus {
+    #[default]
+    Scheduled,
+    Completed,
+    Cancelled,
+}
+
+/// How the event files are arranged in [App::events_dir] (see [crate::config::Config::layout]). Events are read from
+/// both layouts, the layout only decides where events are written to. Use [migrate_layout] to move existing events
+/// after changing it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// All event files are stored directly in [App::events_dir]
+    #[default]
+    Flat,
+    /// Event files are stored in a subdirectory per month of their [Event::date_time] (in UTC), e.g. `2022-04/5.json`,
+    /// so range queries only have to read the directories of the months in the range
+    Monthly,
+}
+
+/// The fields of an event that's about to be created, without an id (see [store_many])
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventDraft {
+    pub name: String,
+    pub description: Option<String>,
+    pub date_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+}
+
+/// Changes to apply to some fields of a stored event, fields that are `None` are left unchanged (see [patch_event]).
+/// `description` is doubly optional, so `Some(None)` removes the description while `None` keeps it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventPatch {
+    pub name: Option<String>,
+    pub description: Option<Option<String>>,
+    pub date_time: Option<DateTime<Utc>>,
+}
+
+impl Event {
+    pub fn new(
+        name: &str,
+        description: Option<&str>,
+        date_time: DateTime<Utc>,
+        end_time: Option<DateTime<Utc>>,
+        app: &App,
+    ) -> Result<Self> {
+        // Validate before generating the id, so invalid events don't use up ids
+        let mut event = Self::unsaved(name, description, date_time, end_time, app.now())?;
+        event.validate_for(app)?;
+        event.assign_id(app)?;
+
+        Ok(event)
+    }
+
+    /// Like [Event::new], but uses the given id instead of generating one, e.g. to recreate an event from a backup.
+    /// Fails with [CalendarError::IdTaken] if an event with that id is already stored. The id is reserved, so it won't
+    /// be generated for another event
+    pub fn with_id(
+        name: &str,
+        description: Option<&str>,
+        date_time: DateTime<Utc>,
+        id: u128,
+        app: &App,
+    ) -> Result<Self> {
+        let mut event = Self::unsaved(name, description, date_time, None, app.now())?;
+        event.validate_for(app)?;
+
+        if !reserve_id(app, id)? {
+            return Err(CalendarError::IdTaken(id));
+        }
+        event.id = id;
+
+        Ok(event)
+    }
+
+    /// Like [Event::new], but without generating an id (the id stays 0 until [Event::assign_id] is called), created at
+    /// `now` (usually [App::now]). Used for events that might never be stored, e.g. when previewing an import
+    pub(crate) fn unsaved(
+        name: &str,
+        description: Option<&str>,
+        date_time: DateTime<Utc>,
+        end_time: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Result<Self> {
+        let event = Self {
+            name: name.to_string(),
+            description: description.map(String::from),
+            location: None,
+            attendees: Vec::new(),
+            date_time,
+            end_time,
+            all_day: false,
+            tz: Tz::UTC,
+            recurrence: None,
+            tags: Vec::new(),
+            priority: Priority::Normal,
+            status: EventStatus::Scheduled,
+            color: None,
+            url: None,
+            reminders: Vec::new(),
+
+            created_at: now,
+            updated_at: now,
+
+            id: 0,
+        };
+        event.validate()?;
+
+        Ok(event)
+    }
+
+    /// Changes the id of the event, so it replaces the stored event with that id when it's stored
+    pub(crate) fn set_id(&mut self, id: u128) {
+        self.id = id;
+    }
+
+    /// Changes when the event was created and last updated, e.g. when it's recreated from another representation
+    pub(crate) fn set_timestamps(&mut self, created_at: DateTime<Utc>, updated_at: DateTime<Utc>) {
+        self.created_at = created_at;
+        self.updated_at = updated_at;
+    }
+
+    /// Gives the event a newly generated id (see [generate_id])
+    pub(crate) fn assign_id(&mut self, app: &App) -> Result<()> {
+        self.id = generate_id(app)?;
+
+        Ok(())
+    }
+
+    /// Sets the tags of the event (surrounding whitespace is removed and empty tags are ignored)
+    pub fn with_tags<S: AsRef<str>>(mut self, tags: &[S]) -> Self {
+        self.tags = tags
+            .iter()
+            .map(|tag| tag.as_ref().trim())
+            .filter(|tag| !tag.is_empty())
+            .map(String::from)
+            .collect();
+
+        self
+    }
+
+    /// Sets the location of the event
+    pub fn with_location(mut self, location: &str) -> Self {
+        self.location = Some(location.to_string());
+        self
+    }
+
+    /// Sets the attendees of the event (surrounding whitespace is removed and empty attendees are ignored)
+    pub fn with_attendees<S: AsRef<str>>(mut self, attendees: &[S]) -> Self {
+        self.attendees = attendees
+            .iter()
+            .map(|attendee| attendee.as_ref().trim())
+            .filter(|attendee| !attendee.is_empty())
+            .map(String::from)
+            .collect();
+
+        self
+    }
+
+    /// Sets the priority of the event
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sets the color of the event (see [Event::color]). Fails if the color is neither a hex code nor one of
+    /// [NAMED_COLORS]
+    pub fn with_color(mut self, color: &str) -> Result<Self> {
+        validate_color(color)?;
+        self.color = Some(color.to_string());
+
+        Ok(self)
+    }
+
+    /// Sets the link of the event (see [Event::url]). Fails if `url` isn't a valid absolute URL
+    pub fn with_url(mut self, url: &str) -> Result<Self> {
+        validate_url(url)?;
+        self.url = Some(url.to_string());
+
+        Ok(self)
+    }
+
+    /// Sets the reminders of the event (see [Event::reminders])
+    pub fn with_reminders(mut self, reminders: &[Duration]) -> Self {
+        self.reminders = reminders.to_vec();
+        self
+    }
+
+    /// Sets the timezone of the event (see [Event::tz])
+    pub fn with_timezone(mut self, tz: Tz) -> Self {
+        self.tz = tz;
+        self
+    }
+
+    /// Returns the time at which the event occurs in the timezone of the event
+    pub fn local_time(&self) -> DateTime<Tz> {
+        self.date_time.with_timezone(&self.tz)
+    }
+
+    /// Formats the event like its [fmt::Display] form (start time, name and description), but with the start time in
+    /// `tz` instead of the timezone of the event
+    pub fn display_in(&self, tz: Tz) -> String {
+        let mut display = format!(
+            "{}  {}",
+            self.date_time.with_timezone(&tz).format("%Y-%m-%d %H:%M"),
+            self.name
+        );
+
+        if let Some(description) = &self.description {
+            display.push_str(" — ");
+            display.push_str(&description.lines().collect::<Vec<_>>().join(" "));
+        }
+
+        display
+    }
+
+    /// Returns whether the event has the given tag (ignoring case and surrounding whitespace)
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = tag.trim().to_lowercase();
+
+        self.tags
+            .iter()
+            .any(|event_tag| event_tag.trim().to_lowercase() == tag)
+    }
+
+    /// Makes the event an all-day event (see [Event::all_day])
+    pub fn with_all_day(mut self, all_day: bool) -> Self {
+        self.all_day = all_day;
+        self
+    }
+
+    /// Returns the time at which the event starts. That's [Event::date_time], except for all-day events which start at
+    /// the beginning of the day of [Event::date_time] (in [Event::tz])
+    pub fn start(&self) -> DateTime<Utc> {
+        if self.all_day {
+            let date = self.local_time().naive_local().date();
+            utils::local_to_utc(&self.tz, date.and_hms(0, 0, 0))
+        } else {
+            self.date_time
+        }
+    }
+
+    /// Returns the time at which the event ends ([Event::date_time] for instantaneous events). All-day events end at
+    /// the beginning of the day after their last day
+    pub fn end(&self) -> DateTime<Utc> {
+        let end = self.end_time.unwrap_or(self.date_time);
+
+        if self.all_day {
+            let last_day = end.with_timezone(&self.tz).naive_local().date();
+            utils::local_to_utc(&self.tz, last_day.succ().and_hms(0, 0, 0))
+        } else {
+            end
+        }
+    }
+
+    /// Returns whether the event is within `[start, end)` like [read_events_in_range] checks it (ignoring the status)
+    pub(crate) fn in_range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        if self.duration().is_zero() {
+            self.start() >= start && self.start() < end
+        } else {
+            self.start() < end && self.end() > start
+        }
+    }
+
+    /// Returns how long the event lasts (zero for instantaneous events)
+    pub fn duration(&self) -> Duration {
+        self.end() - self.start()
+    }
+
+    /// Returns whether the event is over at `now`: whether it has ended (see [Event::end]) at or before `now`.
+    /// Instantaneous events are only past after their time, at their exact time they're in progress (see
+    /// [Event::contains_time])
+    pub fn is_past(&self, now: DateTime<Utc>) -> bool {
+        if self.duration().is_zero() {
+            self.start() < now
+        } else {
+            self.end() <= now
+        }
+    }
+
+    /// Returns whether the event hasn't started yet at `now` (see [Event::start]). Events that are neither past nor
+    /// future are in progress (see [Event::contains_time])
+    pub fn is_future(&self, now: DateTime<Utc>) -> bool {
+        self.start() > now
+    }
+
+    /// Returns whether the event is in progress at `time`: whether `time` is within `[start, end)` (see [Event::start]
+    /// and [Event::end]), so an event isn't in progress anymore at the moment it ends. Instantaneous events are only in
+    /// progress at their exact time
+    pub fn contains_time(&self, time: DateTime<Utc>) -> bool {
+        if self.duration().is_zero() {
+            self.start() == time
+        } else {
+            self.start() <= time && time < self.end()
+        }
+    }
+
+    /// Returns whether the time intervals of the two events intersect. An event covers the half-open interval from
+    /// [Event::start] to [Event::end] (all-day events cover their whole days), so events that only touch (one ends
+    /// exactly when the other starts) don't overlap. Instantaneous events are treated as points: they overlap events
+    /// that are in progress at that point and other instantaneous events at the exact same time. Recurrences are not
+    /// expanded, only the first occurrence of each event is compared
+    pub fn overlaps(&self, other: &Event) -> bool {
+        let (a, b) = (self, other);
+
+        if a.duration().is_zero() && b.duration().is_zero() {
+            return a.start() == b.start();
+        }
+
+        a.start().max(b.start()) < a.end().min(b.end())
+            || (a.duration().is_zero() && a.start() >= b.start() && a.start() < b.end())
+            || (b.duration().is_zero() && b.start() >= a.start() && b.start() < a.end())
+    }
+
+    /// Checks that the fields of the event are valid and consistent with each other
+    pub(crate) fn validate(&self) -> Result<()> {
+        validate_name(&self.name)?;
+
+        if let Some(end_time) = self.end_time {
+            if end_time < self.date_time {
+                return Err(CalendarError::InvalidEvent(format!(
+                    "end time {} of `{}` is before its start time {}",
+                    end_time, self.name, self.date_time
+                )));
+            }
+        }
+
+        if let Some(color) = &self.color {
+            validate_color(color)?;
+        }
+
+        if let Some(url) = &self.url {
+            validate_url(url)?;
+        }
+
+        self.validate_years()?;
+
+        if let Some(reminder) = self
+            .reminders
+            .iter()
+            .find(|reminder| **reminder < Duration::zero())
+        {
+            return Err(CalendarError::InvalidEvent(format!(
+                "reminder of `{}` is {} after the event starts instead of before",
+                self.name, -*reminder
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the event is within the supported years ([MIN_YEAR] to [MAX_YEAR]): its [Event::start] and
+    /// [Event::end], both in UTC and in the event's timezone. So an all-day event on the last day of [MAX_YEAR] isn't
+    /// supported, because it ends at the beginning of the following year. Recurrences aren't checked
+    pub(crate) fn validate_years(&self) -> Result<()> {
+        for date_time in [self.start(), self.end()] {
+            let local_year = date_time.with_timezone(&self.tz).year();
+
+            for year in [date_time.year(), local_year] {
+                if !(MIN_YEAR..=MAX_YEAR).contains(&year) {
+                    return Err(CalendarError::InvalidEvent(format!(
+                        "{} of `{}` is outside of the supported years {} to {}",
+                        date_time, self.name, MIN_YEAR, MAX_YEAR
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Like [Event::validate], but also runs the checks that depend on the config of `app`: if
+    /// [crate::config::Config::year_bounds] is set, the start and end time have to be within those years
+    pub(crate) fn validate_for(&self, app: &App) -> Result<()> {
+        self.validate()?;
+
+        if let Some((first_year, last_year)) = app.config.year_bounds {
+            for date_time in std::iter::once(self.date_time).chain(self.end_time) {
+                if !(first_year..=last_year).contains(&date_time.year()) {
+                    return Err(CalendarError::InvalidEvent(format!(
+                        "{} of `{}` is outside of the years {} to {}",
+                        date_time, self.name, first_year, last_year
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns when the event was created
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    /// Returns when the event was last updated
+    pub fn updated_at(&self) -> DateTime<Utc> {
+        self.updated_at
+    }
+
+    /// Returns the unique id of the event
+    pub fn id(&self) -> u128 {
+        self.id
+    }
+
+    /// Returns all points in time within `[start, end)` at which the event occurs. For recurring events these are the
+    /// expanded occurrences of [Event::recurrence], otherwise it's at most [Event::date_time] itself
+    pub fn occurrences(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        match &self.recurrence {
+            Some(recurrence) => recurrence.occurrences(self.local_time(), start, end),
+            None if self.date_time >= start && self.date_time < end => vec![self.date_time],
+            None => Vec::new(),
+        }
+    }
+
+    /// Reads and deserializes the event with the given id from [App::events_dir]. Fails if there is no event with that
+    /// id or if the file can't be deserialized
+    pub fn load(app: &App, id: u128) -> Result<Self> {
+        let path = find_event_file(app, id).ok_or(CalendarError::NotFound(id))?;
+
+        read_event_file(app, &path)
+    }
+
+    /// Serializes the event with the [App::codec] and writes it to the filesystem. The event gets written to
+    /// [App::events_dir] (or its month directory, depending on the [Layout]). The filename is equal to the unique id of
+    /// the event with the extension of the codec (e.g. `.json`). The event is written to a temporary file first and
+    /// then renamed, so readers never see a partially written file
+    pub fn store(&self, app: &App) -> Result<()> {
+        self.validate_for(app)?;
+
+        let _lock = lock(app)?;
+
+        write_event(app, self)
+    }
+
+    /// Stores an event that is being recreated (e.g. from a backup, see [crate::backup::import_all]), keeping its id if
+    /// no stored event uses it yet and generating a new one otherwise. Kept ids are reserved, so [generate_id] doesn't
+    /// hand them out later
+    pub(crate) fn restore(&mut self, app: &App) -> Result<()> {
+        self.validate_for(app)?;
+
+        if !reserve_id(app, self.id)? {
+            self.id = generate_id(app)?;
+        }
+
+        self.store(app)
+    }
+
+    /// Overwrites the stored file of an already stored event with its current state, keeping the id (and therefore the
+    /// filename) unchanged. The new contents are written to a temporary file first and then renamed over the old file,
+    /// so the old version stays intact if writing fails. Sets [Event::updated_at] to the current time
+    pub fn update(&mut self, app: &App) -> Result<()> {
+        let legacy_path = legacy_event_path(app, self.id);
+
+        if find_event_file(app, self.id).is_none() {
+            return Err(CalendarError::NotFound(self.id));
+        }
+
+        self.validate_for(app)?;
+        self.updated_at = app.now();
+
+        let _lock = lock(app)?;
+        write_event(app, self)?;
+
+        if legacy_path.is_file() {
+            fs::remove_file(&legacy_path).with_context(|| {
+                format!(
+                    "failed to remove old event file `{}`",
+                    legacy_path.to_string_lossy()
+                )
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Changes the status of the already stored event and updates its file (see [Event::update])
+    pub fn set_status(&mut self, app: &App, status: EventStatus) -> Result<()> {
+        self.status = status;
+        self.update(app)
+    }
+
+    /// Deletes the file associated with the event (see [delete_event])
+    pub fn delete_file(&self, app: &App) -> Result<()> {
+        delete_event(app, self.id)
+    }
+
+    /// Returns all fields besides [Event::date_time] and the id for ordering events (see [Ord]), timezones are compared
+    /// by name
+    fn other_fields(&self) -> impl Ord + '_ {
+        (
+            (
+                &self.name,
+                &self.description,
+                &self.location,
+                &self.attendees,
+                self.end_time,
+                self.all_day,
+                self.tz.name(),
+                &self.recurrence,
+            ),
+            (
+                &self.tags,
+                self.priority,
+                self.status,
+                &self.color,
+                &self.url,
+                &self.reminders,
+                self.created_at,
+                self.updated_at,
+            ),
+        )
+    }
+
+    /// Returns the path of the file the event is written to, depending on the [Layout] in the config of `app`
+    fn storage_path(&self, app: &App) -> PathBuf {
+        match app.config.layout {
+            Layout::Flat => event_path(app, self.id),
+            Layout::Monthly => app
+                .events_dir
+                .join(self.date_time.format(MONTH_FORMAT).to_string())
+                .join(event_file_name(app, self.id)),
+        }
+    }
+
+    /// Encodes the event with the [App::codec] of `app`, compressing it if the config says so
+    fn to_bytes(&self, app: &App) -> Result<Vec<u8>> {
+        let encoded = app.codec.encode(self)?;
+
+        if !app.config.compress {
+            return Ok(encoded);
+        }
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(&encoded)
+            .and_then(|_| encoder.finish())
+            .with_context(|| format!("failed to compress event `{}` (id: {})", self.name, self.id))
+    }
+}
+
+impl Eq for Event {}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Events are ordered chronologically by [Event::date_time], events at the same time are ordered by id. To be
+/// consistent with [PartialEq], events that only differ in their other fields (e.g. two versions of the same event)
+/// are ordered by those fields, in the order they're declared in
+impl Ord for Event {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.date_time, self.id)
+            .cmp(&(other.date_time, other.id))
+            .then_with(|| self.other_fields().cmp(&other.other_fields()))
+    }
+}
+
+/// Formats the event as a single line with its local start time and name, followed by the description (if any) with
+/// line breaks replaced by spaces, e.g. `2022-04-04 10:00  Event Name — Description`
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.display_in(self.tz))
+    }
+}
+
+/// The creation and update time of events that were stored without one
+fn epoch() -> DateTime<Utc> {
+    DateTime::from(std::time::UNIX_EPOCH)
+}
+
+/// The timezone of events that were stored without one
+fn default_tz() -> Tz {
+    Tz::UTC
+}
+
+/// Checks that `name` can be used as the name of an event: it can't be empty (or only consist of whitespace) and can't
+/// be longer than [MAX_NAME_LENGTH] characters
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        return Err(CalendarError::InvalidName(
+            "name can't be empty".to_string(),
+        ));
+    }
+
+    let length = name.chars().count();
+
+    if length > MAX_NAME_LENGTH {
+        return Err(CalendarError::InvalidName(format!(
+            "name is {} characters long, the maximum is {}",
+            length, MAX_NAME_LENGTH
+        )));
+    }
+
+    Ok(())
+}
+
+/// Checks that `color` can be used as the color of an event: a hex code like `#1E90FF` (`#RRGGBB`) or one of
+/// [NAMED_COLORS] (both case-insensitive)
+pub fn validate_color(color: &str) -> Result<()> {
+    let is_hex = color
+        .strip_prefix('#')
+        .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()));
+    let is_named = NAMED_COLORS
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case(color));
+
+    if is_hex || is_named {
+        Ok(())
+    } else {
+        Err(CalendarError::InvalidEvent(format!(
+            "invalid color `{}`, expected `#RRGGBB` or one of {}",
+            color,
+            NAMED_COLORS.join(", ")
+        )))
+    }
+}
+
+/// Checks that `url` can be used as the link of an event: an absolute URL with a scheme, like
+/// `https://example.com/call` or `mailto:alice@example.com`
+pub fn validate_url(url: &str) -> Result<()> {
+    Url::parse(url)
+        .map(|_| ())
+        .map_err(|e| CalendarError::InvalidEvent(format!("invalid url `{}`: {}", url, e)))
+}
+
+/// The event format used by evnt 0.1, which stored events as bincode files without an extension
+#[derive(Deserialize)]
+struct LegacyEvent {
+    name: String,
+    description: Option<String>,
+    date_time: DateTime<Utc>,
+    id: u128,
+}
+
+impl From<LegacyEvent> for Event {
+    fn from(legacy: LegacyEvent) -> Self {
+        Self {
+            name: legacy.name,
+            description: legacy.description,
+            location: None,
+            attendees: Vec::new(),
+            date_time: legacy.date_time,
+            end_time: None,
+            all_day: false,
+            tz: Tz::UTC,
+            recurrence: None,
+            tags: Vec::new(),
+            priority: Priority::Normal,
+            status: EventStatus::Scheduled,
+            color: None,
+            url: None,
+            reminders: Vec::new(),
+            created_at: epoch(),
+            updated_at: epoch(),
+            id: legacy.id,
+        }
+    }
+}
+
+/// Deletes the event with the given id without having to read it first, by moving its file (and its notes, see
+/// [get_notes]) to the trash directory `.trash` in [App::events_dir], keeping the name of the file. Events in the trash
+/// aren't read anymore, but can be brought back with [restore_event] until [empty_trash] is called. Fails with
+/// [CalendarError::NotFound] if there is no such event
+pub fn delete_event(app: &App, id: u128) -> Result<()> {
+    let _lock = lock(app)?;
+
+    if find_event_file(app, id).is_none() {
+        return Err(CalendarError::NotFound(id));
+    }
+
+    // Also moves any leftover copies with another padding of the id, so they can't turn up again
+    trash_event_files(app, id, find_event_files(app, id))
+}
+
+/// Deletes all events in [App::events_dir] for which `pred` returns `true` (moving them to the trash like
+/// [delete_event]) and returns how many were deleted, e.g. `delete_where(app, |event| event.date_time < cutoff)` to
+/// remove past events. All events are read before the first one is deleted, so unreadable files make the function fail
+/// without deleting anything. If deleting one of the events fails, the error is a [CalendarError::PartialDelete] with
+/// the ids of the events that were deleted before
+pub fn delete_where(app: &App, pred: impl Fn(&Event) -> bool) -> Result<usize> {
+    let _lock = lock(app)?;
+    let mut matches = Vec::new();
+
+    for path in event_files(app)? {
+        let event = parse_event_file(app, &path)?;
+
+        if pred(&event) {
+            matches.push((event.id, path));
+        }
+    }
+
+    let mut deleted = Vec::new();
+
+    for (id, path) in matches {
+        match trash_event_files(app, id, vec![path]) {
+            Ok(()) => deleted.push(id),
+            Err(e) if deleted.is_empty() => return Err(e),
+            Err(e) => {
+                return Err(CalendarError::PartialDelete {
+                    deleted,
+                    source: Box::new(e),
+                })
+            }
+        }
+    }
+
+    Ok(deleted.len())
+}
+
+/// Moves the event files at `paths` and the notes of the event with the given id to the trash (see [delete_event]).
+/// Must only be called while the events directory is locked
+fn trash_event_files(app: &App, id: u128, paths: Vec<PathBuf>) -> Result<()> {
+    let trash_dir = app.events_dir.join(TRASH_DIR);
+
+    fs::create_dir_all(&trash_dir).with_context(|| {
+        format!(
+            "failed to create directory `{}`",
+            trash_dir.to_string_lossy()
+        )
+    })?;
+
+    let notes = notes_path(app, id);
+    let mut moves = paths;
+    if notes.is_file() {
+        moves.push(notes);
+    }
+
+    for path in moves {
+        // Paths of event and notes files always have a file name
+        let trashed = trash_dir.join(path.file_name().unwrap_or_default());
+
+        fs::rename(&path, &trashed)
+            .with_context(|| format!("failed to move `{}` to the trash", path.to_string_lossy()))?;
+    }
+
+    Ok(())
+}
+
+/// Moves the event with the given id that was deleted with [delete_event] out of the trash again (to where the [Layout]
+/// stores it, converting old files to the [App::codec]) together with its notes, and returns it. Fails with
+/// [CalendarError::NotFound] if the event isn't in the trash and with [CalendarError::IdTaken] if there is another
+/// event with its id
+pub fn restore_event(app: &App, id: u128) -> Result<Event> {
+    let _lock = lock(app)?;
+    let trash_dir = app.events_dir.join(TRASH_DIR);
+    let mut trashed = Vec::new();
+
+    // The trash is searched for the id instead of a file name, since the file might have been padded differently (see
+    // [crate::config::Config::id_width])
+    if trash_dir.is_dir() {
+        for path in iter_dir(&trash_dir, false)? {
+            let path = path?;
+
+            if event_id(&path) == Some(id) {
+                trashed.push(path);
+            }
+        }
+    }
+
+    let path = trashed.first().ok_or(CalendarError::NotFound(id))?;
+
+    if event_exists(app, id) {
+        return Err(CalendarError::IdTaken(id));
+    }
+
+    let event = parse_event_file(app, path)?;
+    write_event(app, &event)?;
+    for path in &trashed {
+        fs::remove_file(path).with_context(|| {
+            format!(
+                "failed to remove restored file `{}` from the trash",
+                path.to_string_lossy()
+            )
+        })?;
+    }
+
+    let notes = trash_dir.join(format!("{}.{}", id, NOTES_EXTENSION));
+    if notes.is_file() {
+        fs::rename(&notes, notes_path(app, id)).with_context(|| {
+            format!("failed to restore notes file `{}`", notes.to_string_lossy())
+        })?;
+    }
+
+    Ok(event)
+}
+
+/// Permanently deletes all events in the trash (see [delete_event]) and returns how many there were
+pub fn empty_trash(app: &App) -> Result<usize> {
+    let _lock = lock(app)?;
+    let trash_dir = app.events_dir.join(TRASH_DIR);
+
+    if !trash_dir.is_dir() {
+        return Ok(0);
+    }
+
+    // Counts ids rather than files, in case an event was trashed with leftover copies
+    let count = iter_dir(&trash_dir, false)?
+        .filter_map(|path| event_id(&path.ok()?))
+        .collect::<HashSet<_>>()
+        .len();
+    fs::remove_dir_all(&trash_dir).with_context(|| {
+        format!(
+            "failed to remove directory `{}`",
+            trash_dir.to_string_lossy()
+        )
+    })?;
+
+    Ok(count)
+}
+
+/// Returns the notes of the event with the given id (markdown text, stored in a sidecar file next to the events, see
+/// [notes_path]), or `None` if the event has no notes. This keeps longer texts out of the event files, so reading
+/// events stays fast. Fails with [CalendarError::NotFound] if there is no such event
+pub fn get_notes(app: &App, id: u128) -> Result<Option<String>> {
+    if !event_exists(app, id) {
+        return Err(CalendarError::NotFound(id));
+    }
+
+    let path = notes_path(app, id);
+
+    match fs::read_to_string(&path) {
+        Ok(notes) => Ok(Some(notes)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e)
+            .with_context(|| format!("failed to read notes file `{}`", path.to_string_lossy())),
+    }
+}
+
+/// Sets the notes of the event with the given id (see [get_notes]), replacing any previous notes. Empty notes remove
+/// the sidecar file. Fails with [CalendarError::NotFound] if there is no such event
+pub fn set_notes(app: &App, id: u128, notes: &str) -> Result<()> {
+    let _lock = lock(app)?;
+
+    if !event_exists(app, id) {
+        return Err(CalendarError::NotFound(id));
+    }
+
+    if notes.is_empty() {
+        remove_notes(app, id)
+    } else {
+        write_atomic(&notes_path(app, id), notes.as_bytes())
+    }
+}
+
+/// Removes the notes file of the event with the given id if there is one. Must only be called while the events
+/// directory is locked
+fn remove_notes(app: &App, id: u128) -> Result<()> {
+    let path = notes_path(app, id);
+
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e)
+            .with_context(|| format!("failed to delete notes file `{}`", path.to_string_lossy())),
+        _ => Ok(()),
+    }
+}
+
+/// Creates and stores an event for each draft, like [Event::new] and [Event::store] would, but the ids for all of them
+/// are reserved at once, so the events directory is only locked and scanned once. Nothing is stored if any of the
+/// drafts is invalid. Returns the created events in the order of the drafts
+pub fn store_many(app: &App, drafts: Vec<EventDraft>) -> Result<Vec<Event>> {
+    let mut events = drafts
+        .into_iter()
+        .map(|draft| {
+            Event::unsaved(
+                &draft.name,
+                draft.description.as_deref(),
+                draft.date_time,
+                draft.end_time,
+                app.now(),
+            )
+        })
+        .collect::<Result<Vec<Event>>>()?;
+
+    for event in &events {
+        event.validate_for(app)?;
+    }
+
+    let _lock = lock(app)?;
+
+    let first_id = next_id(app)?;
+    let count = u128::try_from(events.len()).map_err(|_| CalendarError::IdsExhausted)?;
+    let end_id = first_id
+        .checked_add(count)
+        .ok_or(CalendarError::IdsExhausted)?;
+    set_next_id(app, end_id)?;
+
+    for (event, id) in events.iter_mut().zip(first_id..end_id) {
+        event.id = id;
+        write_new_event(app, event)?;
+    }
+
+    Ok(events)
+}
+
+/// Deletes all events in [App::events_dir] together with their notes (see [get_notes]) and returns how many were
+/// deleted. Files that aren't events (see [event_files]) are left alone. Ids of deleted events aren't handed out again
+pub fn clear_all(app: &App) -> Result<usize> {
+    let _lock = lock(app)?;
+    let paths = event_files(app)?;
+
+    for path in &paths {
+        fs::remove_file(path)
+            .with_context(|| format!("failed to delete event file `{}`", path.to_string_lossy()))?;
+
+        let notes = event_id(path).map(|id| notes_path(app, id));
+
+        if let Some(notes) = notes.filter(|notes| notes.is_file()) {
+            fs::remove_file(&notes).with_context(|| {
+                format!("failed to delete notes file `{}`", notes.to_string_lossy())
+            })?;
+        }
+    }
+
+    Ok(paths.len())
+}
+
+/// Moves all event files to where the [Layout] in the config of `app` stores them and returns how many files were
+/// moved. Files in the old bincode format are converted to the [App::codec] on the way. Month directories that are
+/// empty afterwards are removed
+pub fn migrate_layout(app: &App) -> Result<usize> {
+    let _lock = lock(app)?;
+    let mut moved = 0;
+
+    for path in event_files(app)? {
+        let event = parse_event_file(app, &path)?;
+
+        if event.storage_path(app) != path {
+            // The event is written before the old file is removed, so it's never lost
+            write_event(app, &event)?;
+
+            if path.is_file() {
+                fs::remove_file(&path).with_context(|| {
+                    format!(
+                        "failed to remove old event file `{}`",
+                        path.to_string_lossy()
+                    )
+                })?;
+            }
+            moved += 1;
+        }
+    }
+
+    for (_, dir) in month_dirs(app)? {
+        // Fails for directories that aren't empty, which are kept
+        let _ = fs::remove_dir(dir);
+    }
+
+    Ok(moved)
+}
+
+/// Reschedules the event with the given id to start at `new_time`, keeping its id. The end time (if any) is moved by
+/// the same amount, so the event keeps its duration. Returns the updated event
+pub fn move_event(app: &App, id: u128, new_time: DateTime<Utc>) -> Result<Event> {
+    let mut event = Event::load(app, id)?;
+    reschedule(&mut event, new_time)?;
+    event.update(app)?;
+
+    Ok(event)
+}
+
+/// Sets the [Event::date_time] of the event to `new_time` and moves the end time (if any) by the same amount. Fails
+/// with [CalendarError::InvalidEvent] (leaving the event unchanged) if the moved end time would be out of range
+fn reschedule(event: &mut Event, new_time: DateTime<Utc>) -> Result<()> {
+    if let Some(end_time) = event.end_time {
+        let moved = end_time
+            .checked_add_signed(new_time - event.date_time)
+            .ok_or_else(|| {
+                CalendarError::InvalidEvent(format!(
+                    "the end time of `{}` is out of range when it starts at {}",
+                    event.name, new_time
+                ))
+            })?;
+        event.end_time = Some(moved);
+    }
+    event.date_time = new_time;
+
+    Ok(())
+}
+
+/// Applies the patch to the event with the given id and stores it again, keeping its id. Like [move_event], a new
+/// [Event::date_time] moves the end time (if any) by the same amount. Nothing is changed if the patched event is
+/// invalid. Returns the updated event
+pub fn patch_event(app: &App, id: u128, patch: EventPatch) -> Result<Event> {
+    let mut event = Event::load(app, id)?;
+
+    if let Some(name) = patch.name {
+        event.name = name;
+    }
+    if let Some(description) = patch.description {
+        event.description = description;
+    }
+    if let Some(date_time) = patch.date_time {
+        reschedule(&mut event, date_time)?;
+    }
+    event.update(app)?;
+
+    Ok(event)
+}
+
+/// Changes the name of the event with the given id and stores it again, keeping its id (see [patch_event]). The new
+/// name is validated like the name given to [Event::new] (by [Event::update]). Returns the updated event
+pub fn rename_event(app: &App, id: u128, new_name: &str) -> Result<Event> {
+    patch_event(
+        app,
+        id,
+        EventPatch {
+            name: Some(new_name.to_string()),
+            ..EventPatch::default()
+        },
+    )
+}
+
+/// Reads all the events from [App::events_dir], sorted chronologically by [Event::date_time] (events at the same
+/// time are sorted by id, so the order is the same every time). Events stored in the old bincode format get converted
+/// to the [App::codec]. Fails if any event can't be read, use [read_events_lenient] to skip those instead
+pub fn read_events(app: &App) -> Result<Vec<Event>> {
+    let mut events = event_files(app)?
+        .iter()
+        .map(|path| read_event_file(app, path))
+        .collect::<Result<Vec<_>>>()?;
+
+    events.sort();
+
+    Ok(events)
+}
+
+/// A file that couldn't be read as an event, and the reason why
+pub type ReadFailure = (PathBuf, CalendarError);
+
+/// Like [read_events], but files that can't be read or deserialized don't make the whole function fail. Returns all
+/// events that could be read (sorted like [read_events]) together with the paths of the files that couldn't and why
+pub fn read_events_lenient(app: &App) -> Result<(Vec<Event>, Vec<ReadFailure>)> {
+    let mut events = Vec::new();
+    let mut failures = Vec::new();
+
+    for path in event_files(app)? {
+        match read_event_file(app, &path) {
+            Ok(event) => events.push(event),
+            Err(e) => failures.push((path, e)),
+        }
+    }
+
+    events.sort();
+
+    Ok((events, failures))
+}
+
+/// Like [read_events], but only reads the files that were modified at or after `since` (according to their
+/// modification time), e.g. to refresh a cache of events that was loaded at `since` without deserializing every event
+/// again. Files that change within the same instant as `since` are included, so no change is missed on filesystems with
+/// coarse timestamps.
+///
+/// Events that were deleted since then can't be found this way, callers have to reconcile removals separately
+/// (e.g. by comparing the cached ids with [event_exists]). Events moved between directories without being changed
+/// (see [migrate_layout]) keep their modification time and aren't returned
+pub fn read_events_modified_since(app: &App, since: SystemTime) -> Result<Vec<Event>> {
+    let mut events = Vec::new();
+
+    for path in event_files(app)? {
+        let modified = match fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+            Ok(modified) => modified,
+            // The file was deleted after it was listed
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!(
+                        "failed to read modification time of `{}`",
+                        path.to_string_lossy()
+                    )
+                })
+            }
+        };
+
+        if modified >= since {
+            events.push(read_event_file(app, &path)?);
+        }
+    }
+
+    events.sort();
+
+    Ok(events)
+}
+
+/// Reads at most `limit` events from [App::events_dir], skipping the first `offset` events. Unlike [read_events], the
+/// events are sorted by id (the order they were created in), so only the files of the requested events have to be
+/// read. The order of the remaining events doesn't change when events are added (new events get higher ids), so
+/// reading page after page doesn't skip or repeat events unless events are deleted in between
+pub fn read_events_page(app: &App, offset: usize, limit: usize) -> Result<Vec<Event>> {
+    let mut files: Vec<(u128, PathBuf)> = event_files(app)?
+        .into_iter()
+        .filter_map(|path| Some((event_id(&path)?, path)))
+        .collect();
+    files.sort();
+
+    files
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .map(|(_, path)| read_event_file(app, &path))
+        .collect()
+}
+
+/// Lazily reads the events from [App::events_dir], one file at a time while iterating. The events are yielded in the
+/// order the filesystem lists them in (not sorted like [read_events]). Each event is a separate [Result], so a file
+/// that can't be read doesn't end the iteration
+pub fn iter_events(app: &App) -> Result<impl Iterator<Item = Result<Event>> + '_> {
+    Ok(iter_event_files(app)?.map(move |path| read_event_file(app, &path?)))
+}
+
+/// Returns the number of events in [App::events_dir] by counting the files named like events (see [get_ids]), without
+/// reading them. An event that is stored both in the old bincode format and with a codec (see [read_event_file]) is
+/// only counted once. Unlike [read_events], unreadable files are counted as well
+pub fn count_events(app: &App) -> Result<usize> {
+    Ok(get_ids(app)?.into_iter().collect::<HashSet<_>>().len())
+}
+
+/// Returns the paths of all files in [App::events_dir] that contain events (see [iter_event_files])
+fn event_files(app: &App) -> Result<Vec<PathBuf>> {
+    iter_event_files(app)?.collect()
+}
+
+/// Lazily lists the paths of all files in [App::events_dir] and its month directories (see [Layout::Monthly]) that
+/// contain events, i.e. all files named like [event_path] or [legacy_event_path]. Other files (like the lock file,
+/// temporary files or anything else that ended up in the directory) are skipped. Symlinks are followed (see
+/// [entry_file_type]), so [App::events_dir], month directories and event files can all be symlinks
+fn iter_event_files(app: &App) -> Result<impl Iterator<Item = Result<PathBuf>> + '_> {
+    iter_dir(&app.events_dir, true)
+}
+
+/// An iterator over paths that might fail on every item
+type PathIter = Box<dyn Iterator<Item = Result<PathBuf>>>;
+
+/// Lazily lists the event files in `dir` (see [iter_event_files]), also listing the files in month directories inside
+/// of it if `months` is `true`
+fn iter_dir(dir: &Path, months: bool) -> Result<PathIter> {
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("failed to read directory `{}`", dir.to_string_lossy()))?;
+    let dir = dir.to_path_buf();
+
+    Ok(Box::new(entries.flat_map(move |entry| -> PathIter {
+        let entry = match entry.with_context(|| {
+            format!(
+                "failed to get directory entry from `{}`",
+                dir.to_string_lossy()
+            )
+        }) {
+            Ok(entry) => entry,
+            Err(e) => return Box::new(std::iter::once(Err(e))),
+        };
+
+        match entry_file_type(&entry) {
+            Ok(Some(file_type)) if file_type.is_file() && event_id(&entry.path()).is_some() => {
+                Box::new(std::iter::once(Ok(entry.path())))
+            }
+            Ok(Some(file_type)) if months && file_type.is_dir() && is_month_dir(&entry.path()) => {
+                match iter_dir(&entry.path(), false) {
+                    Ok(files) => files,
+                    Err(e) => Box::new(std::iter::once(Err(e))),
+                }
+            }
+            Ok(_) => Box::new(std::iter::empty()),
+            Err(e) => Box::new(std::iter::once(Err(e))),
+        }
+    })))
+}
+
+/// Returns the file type of the directory entry, following symlinks: for a symlink, the type of the file it points to
+/// is returned, or `None` if it points to nothing (a dangling symlink, which is skipped like any other unrelated
+/// entry). Writing an event whose file is a symlink replaces the symlink with a regular file, since files are written
+/// atomically (see [write_atomic])
+pub(crate) fn entry_file_type(entry: &DirEntry) -> Result<Option<FileType>> {
+    let context = || {
+        format!(
+            "failed to get file type from file `{}`",
+            entry.file_name().to_string_lossy()
+        )
+    };
+    let file_type = entry.file_type().with_context(context)?;
+
+    if !file_type.is_symlink() {
+        return Ok(Some(file_type));
+    }
+
+    match fs::metadata(entry.path()) {
+        Ok(metadata) => Ok(Some(metadata.file_type())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(context),
+    }
+}
+
+/// Returns the month directories (see [Layout::Monthly]) in [App::events_dir] together with the first day of their
+/// month, sorted chronologically
+pub(crate) fn month_dirs(app: &App) -> Result<Vec<(NaiveDate, PathBuf)>> {
+    let mut dirs = Vec::new();
+
+    for entry in fs::read_dir(&app.events_dir).with_context(|| {
+        format!(
+            "failed to read directory `{}`",
+            app.events_dir.to_string_lossy()
+        )
+    })? {
+        let entry = entry.with_context(|| {
+            format!(
+                "failed to get directory entry from `{}`",
+                app.events_dir.to_string_lossy()
+            )
+        })?;
+        let path = entry.path();
+
+        if let Some(month) = path
+            .file_name()
+            .and_then(|name| parse_month(name.to_str()?))
+        {
+            if path.is_dir() {
+                dirs.push((month, path));
+            }
+        }
+    }
+    dirs.sort();
+
+    Ok(dirs)
+}
+
+/// Parses the name of a month directory (like `2022-04`) into the first day of the month. Only the canonical form is
+/// accepted (e.g. not `2022-4`)
+pub(crate) fn parse_month(name: &str) -> Option<NaiveDate> {
+    let month = NaiveDate::parse_from_str(&format!("{}-01", name), "%Y-%m-%d").ok()?;
+
+    (month.format(MONTH_FORMAT).to_string() == name).then_some(month)
+}
+
+/// Returns whether `path` is named like a month directory (see [parse_month])
+fn is_month_dir(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(parse_month)
+        .is_some()
+}
+
+/// Returns whether `path` is directly in [App::events_dir] or in one of its month directories, which is where event
+/// files are stored
+pub(crate) fn is_in_events_dir(app: &App, path: &Path) -> bool {
+    match path.parent() {
+        Some(dir) if dir == app.events_dir => true,
+        Some(dir) => is_month_dir(dir) && dir.parent() == Some(app.events_dir.as_path()),
+        None => false,
+    }
+}
+
+/// Returns the paths of the event files that can contain events that start before `end`. With [Layout::Monthly], the
+/// month directories after the month of `end` are skipped, since events are sorted into the month they start in (in
+/// UTC). Earlier months are all read, because an event that started months before a range can still last into it.
+/// The files directly in [App::events_dir] are always read
+fn event_files_before(app: &App, end: DateTime<Utc>) -> Result<Vec<PathBuf>> {
+    if app.config.layout == Layout::Flat {
+        return event_files(app);
+    }
+
+    let last = NaiveDate::from_ymd(end.year(), end.month(), 1);
+
+    let mut paths = iter_dir(&app.events_dir, false)?.collect::<Result<Vec<_>>>()?;
+
+    for (month, dir) in month_dirs(app)? {
+        if month <= last {
+            paths.extend(iter_dir(&dir, false)?.collect::<Result<Vec<_>>>()?);
+        }
+    }
+
+    Ok(paths)
+}
+
+/// Reads all the events from [App::events_dir] that take place within `[start, end)`, sorted like [read_events].
+/// Events with a duration are included if any part of them (from [Event::start] to [Event::end], so multi-day and
+/// all-day events are included on every day they cover) intersects the range, events that end exactly at `start` or
+/// start exactly at `end` aren't. Instantaneous events are included if they are at `start` or later but before `end`.
+/// Cancelled events (see [EventStatus]) are only included if `include_cancelled` is `true`
+pub fn read_events_in_range(
+    app: &App,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    include_cancelled: bool,
+) -> Result<Vec<Event>> {
+    let (events, failures) = read_events_in_range_lenient(app, start, end, include_cancelled)?;
+
+    match failures.into_iter().next() {
+        Some((_, e)) => Err(e),
+        None => Ok(events),
+    }
+}
+
+/// Like [read_events_in_range], but files that can't be read don't make the whole function fail (see
+/// [read_events_lenient]). Returns the events in the range together with the paths of the files that couldn't be read
+/// and why
+pub fn read_events_in_range_lenient(
+    app: &App,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    include_cancelled: bool,
+) -> Result<(Vec<Event>, Vec<ReadFailure>)> {
+    let mut events = Vec::new();
+    let mut failures = Vec::new();
+
+    for path in event_files_before(app, end)? {
+        match read_event_file(app, &path) {
+            Ok(event) => events.push(event),
+            Err(e) => failures.push((path, e)),
+        }
+    }
+
+    events.retain(|event| {
+        (include_cancelled || event.status != EventStatus::Cancelled) && event.in_range(start, end)
+    });
+    events.sort();
+
+    Ok((events, failures))
+}
+
+/// Like [read_events_in_range], but `start` and `end` are local (wall-clock) times in the timezone `tz` (UTC if
+/// `None`), converted to UTC with [utils::local_to_utc]. This way a range from one local midnight to the next covers
+/// the whole local day, even if it's 23 or 25 hours long because daylight saving time starts or ends
+pub fn read_events_in_local_range(
+    app: &App,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    tz: Option<Tz>,
+    include_cancelled: bool,
+) -> Result<Vec<Event>> {
+    let tz = tz.unwrap_or(Tz::UTC);
+
+    read_events_in_range(
+        app,
+        utils::local_to_utc(&tz, start),
+        utils::local_to_utc(&tz, end),
+        include_cancelled,
+    )
+}
+
+/// Returns the path of the file in which the event with the given id is stored with [Layout::Flat]
+pub(crate) fn event_path(app: &App, id: u128) -> PathBuf {
+    app.events_dir.join(event_file_name(app, id))
+}
+
+/// Returns the name of the file in which the event with the given id is stored, with the id padded to
+/// [crate::config::Config::id_width] digits and the extension of the [App::codec]
+fn event_file_name(app: &App, id: u128) -> String {
+    padded_file_name(app, id, app.codec.extension())
+}
+
+/// Returns the name of the event file with the given id and extension, with the id padded to
+/// [crate::config::Config::id_width] digits
+fn padded_file_name(app: &App, id: u128, extension: &str) -> String {
+    format!("{:0width$}.{}", id, extension, width = app.config.id_width)
+}
+
+/// Returns the name the file at `path` gets when it's renamed to hold the event with the given id, keeping its
+/// extension (or the lack of one for files in the old bincode format)
+pub(crate) fn renamed_event_file(app: &App, path: &Path, id: u128) -> PathBuf {
+    let name = match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => padded_file_name(app, id, extension),
+        None => id.to_string(),
+    };
+
+    path.with_file_name(name)
+}
+
+/// Returns the names the file of the event with the given id can have: like [event_file_name] first, then with the
+/// extensions of the other codecs and unpadded, so files written before the [App::codec] or
+/// [crate::config::Config::id_width] changed are still found
+fn event_file_names(app: &App, id: u128) -> Vec<String> {
+    let mut names = vec![event_file_name(app, id)];
+
+    for extension in codec::EXTENSIONS {
+        for name in [
+            padded_file_name(app, id, extension),
+            format!("{}.{}", id, extension),
+        ] {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+
+    names
+}
+
+/// Returns the path of the sidecar file the notes of the event with the given id are stored in (see [get_notes]). Notes
+/// are always stored directly in [App::events_dir], regardless of the [Layout], so they don't have to be moved with the
+/// event
+pub(crate) fn notes_path(app: &App, id: u128) -> PathBuf {
+    app.events_dir.join(format!("{}.{}", id, NOTES_EXTENSION))
+}
+
+/// Returns whether the file at `path` is named like a notes file (see [notes_path])
+pub(crate) fn is_notes_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == NOTES_EXTENSION)
+        && path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| stem.parse::<u128>().is_ok_and(|id| id.to_string() == stem))
+}
+
+/// Returns the path under which the event with the given id was stored by evnt 0.1 (see [LegacyEvent])
+pub(crate) fn legacy_event_path(app: &App, id: u128) -> PathBuf {
+    app.events_dir.join(id.to_string())
+}
+
+/// Returns whether an event with the given id is stored in [App::events_dir]
+pub(crate) fn event_exists(app: &App, id: u128) -> bool {
+    find_event_file(app, id).is_some()
+}
+
+/// Returns the id of the event stored at `path` if the file is named like an event file ([event_path] with any padding
+/// or [legacy_event_path]), or `None` otherwise
+pub(crate) fn event_id(path: &Path) -> Option<u128> {
+    let extension = path.extension();
+
+    if extension.is_some() && !is_codec_file(path) {
+        return None;
+    }
+
+    let stem = path.file_stem()?.to_str()?;
+    // Codec files may be padded with zeros (see Config::id_width), legacy files never were
+    let digits = match extension {
+        Some(_) if stem.len() > 1 => stem.trim_start_matches('0'),
+        _ => stem,
+    };
+    let digits = if digits.is_empty() { "0" } else { digits };
+    let id = digits.parse::<u128>().ok()?;
+
+    // Only accept the canonical form of the id apart from the padding (e.g. not `+1`), so the path matches the id
+    // exactly
+    (digits == id.to_string()).then_some(id)
+}
+
+/// Returns the path of the file the event with the given id is stored in (in the current or the legacy format), or
+/// `None` if there is no event with that id. The usual paths (see [event_file_candidates]) are checked first, the
+/// events directory is only listed if the event isn't at any of them (e.g. because [crate::config::Config::id_width]
+/// changed)
+fn find_event_file(app: &App, id: u128) -> Option<PathBuf> {
+    event_file_candidates(app, id)
+        .into_iter()
+        .find(|path| path.is_file())
+        .or_else(|| find_event_files(app, id).into_iter().next())
+}
+
+/// Returns the paths of all files in [App::events_dir] and its month directories that are named like files of the event
+/// with the given id (see [event_id]), whatever their padding. Normally there is at most one, but an interrupted
+/// [write_event] can leave an old copy behind
+fn find_event_files(app: &App, id: u128) -> Vec<PathBuf> {
+    iter_event_files(app)
+        .map(|files| {
+            files
+                .filter_map(|path| path.ok())
+                .filter(|path| event_id(path) == Some(id))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the paths at which a file of the event with the given id usually is, padded like the current config or
+/// unpadded. The month directories are only searched with [Layout::Monthly], so lookups in a large flat directory
+/// don't have to list it
+fn event_file_candidates(app: &App, id: u128) -> Vec<PathBuf> {
+    let names = event_file_names(app, id);
+    let mut dirs = vec![app.events_dir.clone()];
+
+    if app.config.layout == Layout::Monthly {
+        dirs.extend(
+            month_dirs(app)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(_, dir)| dir),
+        );
+    }
+
+    let mut candidates: Vec<PathBuf> = dirs
+        .iter()
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .collect();
+    candidates.push(legacy_event_path(app, id));
+
+    candidates
+}
+
+/// Writes the event to its file (see [Event::storage_path]), creating its month directory if necessary. Files of the
+/// event in other places (e.g. in another month directory because the event was moved, or with another padding of the
+/// id or another codec) are removed afterwards, except for files in the old bincode format. Must only be called while
+/// the events directory is locked
+fn write_event(app: &App, event: &Event) -> Result<()> {
+    let path = write_new_event(app, event)?;
+
+    for stale in find_event_files(app, event.id) {
+        if stale != path && is_codec_file(&stale) && stale.is_file() {
+            fs::remove_file(&stale).with_context(|| {
+                format!(
+                    "failed to remove old event file `{}`",
+                    stale.to_string_lossy()
+                )
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the event to its file like [write_event] and returns its path, without looking for old files of the event.
+/// Only for events with freshly generated ids, which can't have any. Must only be called while the events directory is
+/// locked
+fn write_new_event(app: &App, event: &Event) -> Result<PathBuf> {
+    let path = event.storage_path(app);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create directory `{}`", dir.to_string_lossy()))?;
+    }
+
+    write_atomic(&path, &event.to_bytes(app)?)?;
+
+    Ok(path)
+}
+
+/// Reads and deserializes the event stored in the file at `path`. Files without extension are assumed to be in the old
+/// bincode format (see [LegacyEvent]), these get converted to the [App::codec] and the old file is removed. Files
+/// written with another codec are read as they are, they're converted when the event is written again
+fn read_event_file(app: &App, path: &Path) -> Result<Event> {
+    let event = parse_event_file(app, path)?;
+
+    if is_codec_file(path) || app.read_only {
+        return Ok(event);
+    }
+
+    // Convert the event to the current codec, the old file is only removed after the new one has been written
+    let _lock = lock(app)?;
+    write_event(app, &event)?;
+    fs::remove_file(path).with_context(|| {
+        format!(
+            "failed to remove old event file `{}`",
+            path.to_string_lossy()
+        )
+    })?;
+
+    Ok(event)
+}
+
+/// Reads and deserializes the event stored in the file at `path` (encoded with the [App::codec] or a built-in
+/// [codec::Codec] and possibly gzip compressed, or in the old bincode format, see [read_event_file]) without converting
+/// anything
+pub(crate) fn parse_event_file(app: &App, path: &Path) -> Result<Event> {
+    let mut bytes = fs::read(path)
+        .with_context(|| format!("failed to read from file `{}`", path.to_string_lossy()))?;
+
+    if is_codec_file(path) {
+        if bytes.starts_with(&GZIP_MAGIC) {
+            let mut decompressed = Vec::new();
+            GzDecoder::new(bytes.as_slice())
+                .read_to_end(&mut decompressed)
+                .with_context(|| {
+                    format!(
+                        "failed to decompress file `{}` (file is corrupt)",
+                        path.to_string_lossy()
+                    )
+                })?;
+            bytes = decompressed;
+        }
+
+        return codec::decode(&*app.codec, &bytes).map_err(|e| match e {
+            CalendarError::Serialization { source, .. } => CalendarError::Serialization {
+                context: format!(
+                    "failed to deserialize event from file `{}` (file is corrupt)",
+                    path.to_string_lossy()
+                ),
+                source,
+            },
+            e => e,
+        });
+    }
+
+    Ok(bincode::deserialize::<LegacyEvent>(&bytes)
+        .with_context(|| {
+            format!(
+                "failed to deserialize event from file `{}` (file is corrupt)",
+                path.to_string_lossy()
+            )
+        })?
+        .into())
+}
+
+/// Returns whether the file at `path` has the extension of one of the built-in codecs (see [codec::EXTENSIONS]),
+/// instead of being in the old bincode format
+fn is_codec_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| codec::EXTENSIONS.iter().any(|known| extension == *known))
+}
+
+/// Writes `bytes` to a temporary file next to `path` and renames it to `path` afterwards, which replaces the old file
+/// atomically (as long as both are on the same filesystem)
+pub(crate) fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(path.file_name().unwrap_or_default());
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    if let Err(e) = fs::write(&temp_path, bytes) {
+        let _ = fs::remove_file(&temp_path);
+
+        return Err(e).with_context(|| {
+            format!(
+                "failed to write temporary file `{}`",
+                temp_path.to_string_lossy()
+            )
+        });
+    }
+
+    if let Err(e) = fs::rename(&temp_path, path) {
+        let _ = fs::remove_file(&temp_path);
+
+        return Err(e).with_context(|| {
+            format!(
+                "failed to rename `{}` to `{}`",
+                temp_path.to_string_lossy(),
+                path.to_string_lossy()
+            )
+        });
+    }
+
+    Ok(())
+}
+
+/// Acquires an exclusive lock on the lock file in [App::events_dir], blocking until no other process (or thread) holds
+/// it. The lock is released when the returned file is dropped. Everything that writes to [App::events_dir] takes the
+/// lock, so this fails with [CalendarError::ReadOnly] if [App::read_only] is set
+pub(crate) fn lock(app: &App) -> Result<fs::File> {
+    if app.read_only {
+        return Err(CalendarError::ReadOnly);
+    }
+
+    let path = app.events_dir.join(LOCK_FILE);
+
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .with_context(|| format!("failed to open lock file `{}`", path.to_string_lossy()))?;
+
+    file.lock_exclusive()
+        .with_context(|| format!("failed to lock `{}`", path.to_string_lossy()))?;
+
+    Ok(file)
+}
+
+/// Generates a unique id for an event. Ids are handed out in ascending order using a counter stored in
+/// [NEXT_ID_FILE], the counter is incremented (while the events directory is locked) before the id is returned, so each
+/// id is only handed out once even if the event doesn't get stored right away. If the counter doesn't exist yet (or is
+/// unreadable) it's initialized by scanning the existing event ids once
+fn generate_id(app: &App) -> Result<u128> {
+    let _lock = lock(app)?;
+
+    let id = next_id(app)?;
+    set_next_id(app, id.checked_add(1).ok_or(CalendarError::IdsExhausted)?)?;
+
+    Ok(id)
+}
+
+/// Reserves `id` for an event that keeps an id it got elsewhere, by moving the counter in [NEXT_ID_FILE] past it.
+/// Returns `false` (without reserving anything) if an event with that id is already stored
+fn reserve_id(app: &App, id: u128) -> Result<bool> {
+    let _lock = lock(app)?;
+
+    if find_event_file(app, id).is_some() {
+        return Ok(false);
+    }
+
+    if id >= next_id(app)? {
+        set_next_id(app, id.checked_add(1).ok_or(CalendarError::IdsExhausted)?)?;
+    }
+
+    Ok(true)
+}
+
+/// Reads the next id that will be handed out from [NEXT_ID_FILE], scanning the existing event ids if the counter
+/// doesn't exist yet. Must only be called while the events directory is locked
+fn next_id(app: &App) -> Result<u128> {
+    match fs::read_to_string(app.events_dir.join(NEXT_ID_FILE))
+        .ok()
+        .and_then(|next_id| next_id.trim().parse::<u128>().ok())
+    {
+        Some(id) => Ok(id),
+        None => match get_ids(app)?.into_iter().max() {
+            Some(max) => max.checked_add(1).ok_or(CalendarError::IdsExhausted),
+            None => Ok(0),
+        },
+    }
+}
+
+/// Writes the next id that will be handed out to [NEXT_ID_FILE]. Must only be called while the events directory is
+/// locked
+fn set_next_id(app: &App, next_id: u128) -> Result<()> {
+    write_atomic(
+        &app.events_dir.join(NEXT_ID_FILE),
+        next_id.to_string().as_bytes(),
+    )
+}
+
+/// Gets all event ids by reading the names of the event files (see [event_id] and [iter_event_files])
+pub(crate) fn get_ids(app: &App) -> Result<Vec<u128>> {
+    iter_event_files(app)?
+        .filter_map(|path| path.map(|path| event_id(&path)).transpose())
+        .collect()
+}
+
+/// (De)serializes durations as whole seconds, since [Duration] doesn't implement [Serialize] and [Deserialize]
+mod duration_seconds {
+    use chrono::Duration;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    /// The largest number of seconds a [Duration] can hold
+    const MAX_SECONDS: i64 = i64::MAX / 1000;
+
+    pub fn serialize<S: Serializer>(
+        durations: &[Duration],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(durations.iter().map(Duration::num_seconds))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Duration>, D::Error> {
+        Vec::<i64>::deserialize(deserializer)?
+            .into_iter()
+            .map(|seconds| {
+                if (-MAX_SECONDS..=MAX_SECONDS).contains(&seconds) {
+                    Ok(Duration::seconds(seconds))
+                } else {
+                    Err(D::Error::custom(format!(
+                        "duration of {} seconds is out of range",
+                        seconds
+                    )))
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_unique_ids() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let mut ids = Vec::new();
+
+        for _ in 0..1000 {
+            let id = generate_id(&app).unwrap();
+            assert!(!ids.contains(&id));
+
+            ids.push(id);
+
+            fs::File::create(app.data_dir.join(id.to_string())).unwrap();
+        }
+    }
+
+    #[test]
+    fn event_serializes_and_deserializes() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Test Event",
+            Some("Event description"),
+            Utc.ymd(1000, 10, 10).and_hms(14, 30, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
+
+        let events = read_events(&app).unwrap();
+        let read_event = events.first().unwrap();
+
+        assert!(event == *read_event);
+    }
+
+    #[test]
+    fn serializes_and_deserializes_lots_of_events() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let mut original_events = Vec::new();
+
+        for n in 0..100 {
+            let event = Event::new(
+                &n.to_string(),
+                None,
+                Utc.ymd(2000, 10, 10).and_hms(15, 15, 0),
+                None,
+                &app,
+            )
+            .unwrap();
+
+            event.store(&app).unwrap();
+
+            original_events.push(event);
+        }
+
+        let read_events = read_events(&app).unwrap();
+
+        for event in original_events {
+            assert!(read_events.contains(&event));
+        }
+    }
+
+    #[test]
+    fn deletes_events() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Event Name",
+            Some("Event description"),
+            Utc.ymd(2000, 2, 4).and_hms(20, 10, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+
+        event.store(&app).unwrap();
+        assert!(event_path(&app, event.id).exists());
+
+        event.delete_file(&app).unwrap();
+        assert!(!event_path(&app, event.id).exists());
+    }
+
+    #[test]
+    fn displays_events_on_one_line() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let date_time = Utc.ymd(2022, 4, 4).and_hms(10, 0, 0);
+        let event = Event::new("Event Name", None, date_time, None, &app).unwrap();
+        assert_eq!(event.to_string(), "2022-04-04 10:00  Event Name");
+
+        let event = Event::new(
+            "Event Name",
+            Some("Line one\nLine two"),
+            date_time,
+            None,
+            &app,
+        )
+        .unwrap()
+        .with_timezone(Tz::Europe__Berlin);
+        assert_eq!(
+            event.to_string(),
+            "2022-04-04 12:00  Event Name — Line one Line two"
+        );
+    }
+
+    #[test]
+    fn updates_events_in_place() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let mut event = Event::new(
+            "Event Name",
+            Some("Event description"),
+            Utc.ymd(2000, 2, 4).and_hms(20, 10, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
+
+        let id = event.id;
+        event.description = Some("Changed description".to_string());
+        let created_at = event.created_at();
+        event.update(&app).unwrap();
+
+        let events = read_events(&app).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id, id);
+        assert_eq!(events[0], event);
+        assert_eq!(event.created_at(), created_at);
+        assert!(event.updated_at() >= created_at);
+
+        // Events stored before the timestamps existed still deserialize
+        let mut json: serde_json::Value = serde_json::to_value(&event).unwrap();
+        json.as_object_mut().unwrap().remove("created_at");
+        json.as_object_mut().unwrap().remove("updated_at");
+        let old: Event = serde_json::from_value(json).unwrap();
+        assert_eq!(old.created_at(), epoch());
+        assert_eq!(old.updated_at(), epoch());
+    }
+
+    #[test]
+    fn stores_events_atomically() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2000, 2, 4).and_hms(20, 10, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
+        assert_eq!(Event::load(&app, event.id).unwrap(), event);
+
+        // Renaming fails if there is a directory in the way, the temporary file must not be left behind
+        let other = Event::new(
+            "Other Event",
+            None,
+            Utc.ymd(2000, 2, 4).and_hms(20, 10, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        fs::create_dir(event_path(&app, other.id)).unwrap();
+        assert!(other.store(&app).is_err());
+
+        let files: Vec<_> = fs::read_dir(&app.events_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert!(files.iter().all(|name| !name.ends_with(".tmp")));
+    }
+
+    #[test]
+    fn moves_events() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            Some(Utc.ymd(2022, 4, 4).and_hms(11, 0, 0)),
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
+
+        let moved = move_event(&app, event.id, Utc.ymd(2022, 4, 5).and_hms(9, 0, 0)).unwrap();
+
+        assert_eq!(moved.id, event.id);
+        assert_eq!(moved.end_time, Some(Utc.ymd(2022, 4, 5).and_hms(10, 0, 0)));
+        assert_eq!(read_events(&app).unwrap(), vec![moved.clone()]);
+        assert!(matches!(
+            move_event(&app, 1000, Utc.ymd(2022, 4, 5).and_hms(9, 0, 0)),
+            Err(CalendarError::NotFound(1000))
+        ));
+
+        // The end time would be after the latest representable time
+        assert!(matches!(
+            move_event(&app, event.id, chrono::MAX_DATETIME),
+            Err(CalendarError::InvalidEvent(_))
+        ));
+        assert_eq!(read_events(&app).unwrap(), vec![moved]);
+    }
+
+    #[test]
+    fn rejects_years_outside_of_bounds() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let mut app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let typo = Utc.ymd(2202, 4, 4).and_hms(10, 0, 0);
+        let event = Event::new("Event Name", None, typo, None, &app).unwrap();
+
+        app.config.year_bounds = Some((1970, 2100));
+        let error = Event::new("Event Name", None, typo, None, &app).unwrap_err();
+        assert!(matches!(error, CalendarError::InvalidEvent(_)));
+        assert!(error.to_string().contains("2202-04-04 10:00:00 UTC"));
+        assert!(event.store(&app).is_err());
+        assert!(read_events(&app).unwrap().is_empty());
+
+        let date_time = Utc.ymd(2022, 4, 4).and_hms(10, 0, 0);
+        assert!(Event::new("Event Name", None, date_time, Some(typo), &app).is_err());
+        assert!(Event::new("Event Name", None, date_time, None, &app).is_ok());
+    }
+
+    #[test]
+    fn creates_events_with_explicit_ids() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let date_time = Utc.ymd(2022, 4, 4).and_hms(10, 0, 0);
+        let event = Event::with_id("Event Name", None, date_time, 42, &app).unwrap();
+        assert_eq!(event.id, 42);
+        event.store(&app).unwrap();
+
+        assert!(matches!(
+            Event::with_id("Other Event", None, date_time, 42, &app),
+            Err(CalendarError::IdTaken(42))
+        ));
+
+        // Generated ids don't collide with the explicit one
+        let generated = Event::new("Other Event", None, date_time, None, &app).unwrap();
+        assert_eq!(generated.id, 43);
+    }
+
+    #[test]
+    fn patches_events() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Event Name",
+            Some("Description"),
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
+
+        // Fields that aren't patched are left unchanged
+        let renamed = patch_event(
+            &app,
+            event.id,
+            EventPatch {
+                name: Some("New Name".to_string()),
+                ..EventPatch::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(renamed.name, "New Name");
+        assert_eq!(renamed.description.as_deref(), Some("Description"));
+        assert_eq!(renamed.date_time, event.date_time);
+
+        let cleared = patch_event(
+            &app,
+            event.id,
+            EventPatch {
+                description: Some(None),
+                date_time: Some(Utc.ymd(2022, 4, 5).and_hms(10, 0, 0)),
+                ..EventPatch::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(cleared.name, "New Name");
+        assert_eq!(cleared.description, None);
+        assert_eq!(read_events(&app).unwrap(), vec![cleared.clone()]);
+
+        let invalid = EventPatch {
+            name: Some(String::new()),
+            ..EventPatch::default()
+        };
+        assert!(patch_event(&app, event.id, invalid).is_err());
+        assert_eq!(read_events(&app).unwrap(), vec![cleared.clone()]);
+
+        // Moving the end time along would overflow
+        let meeting = Event::new(
+            "Meeting",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            Some(Utc.ymd(2022, 4, 4).and_hms(11, 0, 0)),
+            &app,
+        )
+        .unwrap();
+        meeting.store(&app).unwrap();
+        let out_of_range = EventPatch {
+            date_time: Some(chrono::MAX_DATETIME),
+            ..EventPatch::default()
+        };
+        assert!(matches!(
+            patch_event(&app, meeting.id, out_of_range),
+            Err(CalendarError::InvalidEvent(_))
+        ));
+        assert_eq!(read_events(&app).unwrap(), vec![meeting, cleared]);
+    }
+
+    #[test]
+    fn reads_events_in_local_range() {
+        use chrono::{NaiveDate, TimeZone};
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        // Daylight saving time starts on 2022-03-13 in New York, so that day is only 23 hours long (05:00 UTC on the
+        // 13th to 04:00 UTC on the 14th)
+        let mut events = Vec::new();
+
+        for (name, date_time) in [
+            ("Day before", Utc.ymd(2022, 3, 13).and_hms(4, 59, 0)),
+            ("Midnight", Utc.ymd(2022, 3, 13).and_hms(5, 0, 0)),
+            ("Late evening", Utc.ymd(2022, 3, 14).and_hms(3, 30, 0)),
+            ("Day after", Utc.ymd(2022, 3, 14).and_hms(4, 30, 0)),
+        ] {
+            let event = Event::new(name, None, date_time, None, &app).unwrap();
+            event.store(&app).unwrap();
+            events.push(event);
+        }
+
+        let start = NaiveDate::from_ymd(2022, 3, 13).and_hms(0, 0, 0);
+        let end = NaiveDate::from_ymd(2022, 3, 14).and_hms(0, 0, 0);
+
+        assert_eq!(
+            read_events_in_local_range(&app, start, end, Some(Tz::America__New_York), true)
+                .unwrap(),
+            events[1..3]
+        );
+        // Without a timezone the boundaries are in UTC
+        assert_eq!(
+            read_events_in_local_range(&app, start, end, None, true).unwrap(),
+            events[..2]
+        );
+    }
+
+    #[test]
+    fn renames_events() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Event Name",
+            Some("Description"),
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
+
+        let renamed = rename_event(&app, event.id, "New Name").unwrap();
+        assert_eq!(renamed.name, "New Name");
+        assert_eq!(renamed.description, event.description);
+        assert_eq!(read_events(&app).unwrap(), vec![renamed.clone()]);
+
+        assert!(matches!(
+            rename_event(&app, event.id, ""),
+            Err(CalendarError::InvalidName(_))
+        ));
+        assert!(matches!(
+            rename_event(&app, 1000, "New Name"),
+            Err(CalendarError::NotFound(1000))
+        ));
+        assert_eq!(read_events(&app).unwrap(), vec![renamed]);
+    }
+
+    #[test]
+    fn cancelled_events_can_be_excluded() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let mut event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
+        event.set_status(&app, EventStatus::Cancelled).unwrap();
+        assert_eq!(
+            Event::load(&app, event.id).unwrap().status,
+            EventStatus::Cancelled
+        );
+
+        let start = Utc.ymd(2022, 4, 4).and_hms(0, 0, 0);
+        let end = Utc.ymd(2022, 4, 5).and_hms(0, 0, 0);
+        assert_eq!(
+            read_events_in_range(&app, start, end, true).unwrap(),
+            vec![event.clone()]
+        );
+        assert!(read_events_in_range(&app, start, end, false)
+            .unwrap()
+            .is_empty());
+
+        event.set_status(&app, EventStatus::Completed).unwrap();
+        assert_eq!(
+            read_events_in_range(&app, start, end, false).unwrap(),
+            vec![event]
+        );
+    }
+
+    #[test]
+    fn update_fails_for_unstored_events() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let mut event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2000, 2, 4).and_hms(20, 10, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+
+        assert!(event.update(&app).is_err());
+    }
+
+    #[test]
+    fn loads_events_by_id() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Event Name",
+            Some("Event description"),
+            Utc.ymd(2000, 2, 4).and_hms(20, 10, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
+
+        assert_eq!(Event::load(&app, event.id()).unwrap(), event);
+    }
+
+    #[test]
+    fn load_distinguishes_missing_and_corrupt_events() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        assert!(matches!(
+            Event::load(&app, 1),
+            Err(CalendarError::NotFound(1))
+        ));
+
+        fs::write(app.events_dir.join("2"), b"garbage").unwrap();
+        assert!(matches!(
+            Event::load(&app, 2),
+            Err(CalendarError::Serialization { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_end_time_before_start() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let start = Utc.ymd(2000, 2, 4).and_hms(20, 10, 0);
+
+        assert!(Event::new(
+            "Event Name",
+            None,
+            start,
+            Some(start - Duration::hours(1)),
+            &app
+        )
+        .is_err());
+
+        let event = Event::new(
+            "Event Name",
+            None,
+            start,
+            Some(start + Duration::hours(1)),
+            &app,
+        )
+        .unwrap();
+        assert_eq!(event.duration(), Duration::hours(1));
+    }
+
+    #[test]
+    fn reads_events_in_range() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let start = Utc.ymd(2022, 4, 4).and_hms(0, 0, 0);
+        let end = Utc.ymd(2022, 4, 11).and_hms(0, 0, 0);
+
+        for (name, date_time) in [
+            ("Before", start - Duration::seconds(1)),
+            ("At start", start),
+            ("Inside", start + Duration::days(3)),
+            ("At end", end),
+        ] {
+            Event::new(name, None, date_time, None, &app)
+                .unwrap()
+                .store(&app)
+                .unwrap();
+        }
+
+        let mut names: Vec<String> = read_events_in_range(&app, start, end, true)
+            .unwrap()
+            .into_iter()
+            .map(|event| event.name)
+            .collect();
+        names.sort();
+
+        assert_eq!(names, vec!["At start", "Inside"]);
+    }
+
+    #[test]
+    fn reads_events_sorted() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        for day in [5, 1, 3, 3, 2, 4] {
+            Event::new(
+                &day.to_string(),
+                None,
+                Utc.ymd(2022, 1, day).and_hms(12, 0, 0),
+                None,
+                &app,
+            )
+            .unwrap()
+            .store(&app)
+            .unwrap();
+        }
+
+        let events = read_events(&app).unwrap();
+
+        assert!(events
+            .windows(2)
+            .all(|pair| (pair[0].date_time, pair[0].id) < (pair[1].date_time, pair[1].id)));
+    }
+
+    #[test]
+    fn orders_events_by_time_then_id() {
+        use chrono::TimeZone;
+
+        use crate::recurrence::Frequency;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = |hour| {
+            Event::new(
+                "Event Name",
+                None,
+                Utc.ymd(2022, 1, 1).and_hms(hour, 0, 0),
+                None,
+                &app,
+            )
+            .unwrap()
+        };
+
+        let first = event(10);
+        let second = event(10);
+        let earlier = event(8);
+        assert!(first < second);
+        assert!(earlier < first);
+
+        let mut events = vec![second.clone(), first.clone(), earlier.clone()];
+        events.sort();
+        assert_eq!(events, vec![earlier, first.clone(), second]);
+
+        // Different versions of the same event aren't equal, so they must not compare as equal either
+        let mut renamed = first.clone();
+        renamed.name = "Other Name".to_string();
+        assert_ne!(first.cmp(&renamed), Ordering::Equal);
+        assert_eq!(first.cmp(&renamed), renamed.cmp(&first).reverse());
+        assert!(first < renamed);
+        assert_eq!(first.cmp(&first.clone()), Ordering::Equal);
+
+        let mut recurring = first.clone();
+        recurring.recurrence = Some(Recurrence::new(Frequency::Daily, None));
+        let mut weekly = recurring.clone();
+        weekly.recurrence = Some(Recurrence::new(
+            Frequency::Weekly {
+                weekday: chrono::Weekday::Mon,
+            },
+            None,
+        ));
+        assert!(first < recurring);
+        assert!(recurring < weekly);
+    }
+
+    #[test]
+    fn reads_pages_of_events() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        // 12 events, so ids have different numbers of digits (sorting by file name would put 10 before 2)
+        let mut events = Vec::new();
+
+        for day in (1..=12).rev() {
+            let event = Event::new(
+                "Event Name",
+                None,
+                Utc.ymd(2022, 1, day).and_hms(12, 0, 0),
+                None,
+                &app,
+            )
+            .unwrap();
+            event.store(&app).unwrap();
+            events.push(event);
+        }
+
+        assert_eq!(read_events_page(&app, 0, 5).unwrap(), events[0..5]);
+        assert_eq!(read_events_page(&app, 5, 5).unwrap(), events[5..10]);
+        assert_eq!(read_events_page(&app, 10, 5).unwrap(), events[10..]);
+        assert!(read_events_page(&app, 20, 5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn counts_events_without_reading_them() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+        assert_eq!(count_events(&app).unwrap(), 0);
+
+        let event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2022, 1, 1).and_hms(12, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
+        fs::copy(
+            event_path(&app, event.id),
+            legacy_event_path(&app, event.id),
+        )
+        .unwrap();
+        fs::write(event_path(&app, 1000), "{ not json").unwrap();
+        fs::write(app.events_dir.join("README.txt"), "not an event").unwrap();
+
+        assert_eq!(count_events(&app).unwrap(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn follows_symlinks() {
+        use chrono::TimeZone;
+        use std::os::unix::fs::symlink;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let real_dir = temp_data_dir.path().join("real");
+        let data_dir = temp_data_dir.path().join("data");
+        fs::create_dir_all(real_dir.join("events")).unwrap();
+        fs::create_dir(&data_dir).unwrap();
+        // The events directory itself is a symlink
+        symlink(real_dir.join("events"), data_dir.join("events")).unwrap();
+        let app = App::new(&data_dir);
+        crate::utils::create_dirs(&app).unwrap();
+
+        let date_time = Utc.ymd(2022, 4, 4).and_hms(10, 0, 0);
+        let event = Event::new("Linked", None, date_time, None, &app).unwrap();
+        event.store(&app).unwrap();
+        let target = real_dir.join("linked.json");
+        fs::rename(event_path(&app, event.id), &target).unwrap();
+        symlink(&target, event_path(&app, event.id)).unwrap();
+        // Dangling symlinks are skipped
+        symlink(real_dir.join("missing.json"), event_path(&app, 1000)).unwrap();
+
+        assert_eq!(read_events(&app).unwrap(), vec![event.clone()]);
+        assert_eq!(get_ids(&app).unwrap(), vec![event.id]);
+    }
+
+    #[test]
+    fn iterates_over_events_lazily() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        for day in 1..=3 {
+            Event::new(
+                "Event Name",
+                None,
+                Utc.ymd(2022, 1, day).and_hms(12, 0, 0),
+                None,
+                &app,
+            )
+            .unwrap()
+            .store(&app)
+            .unwrap();
+        }
+        fs::write(event_path(&app, 1000), "{ not json").unwrap();
+
+        let results: Vec<Result<Event>> = iter_events(&app).unwrap().collect();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results.iter().filter(|result| result.is_err()).count(), 1);
+
+        let mut events: Vec<Event> = iter_events(&app).unwrap().filter_map(Result::ok).collect();
+        events.sort();
+        assert_eq!(events, read_events_lenient(&app).unwrap().0);
+
+        assert_eq!(iter_events(&app).unwrap().take(2).count(), 2);
+    }
+
+    #[test]
+    fn reads_compressed_and_uncompressed_events() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let mut app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let date_time = Utc.ymd(2022, 4, 4).and_hms(10, 0, 0);
+        let plain = Event::new("Plain", None, date_time, None, &app).unwrap();
+        plain.store(&app).unwrap();
+
+        app.config.compress = true;
+        let mut compressed = Event::new("Compressed", None, date_time, None, &app).unwrap();
+        compressed.store(&app).unwrap();
+
+        let bytes = fs::read(event_path(&app, compressed.id)).unwrap();
+        assert!(bytes.starts_with(&GZIP_MAGIC));
+        assert!(fs::read(event_path(&app, plain.id))
+            .unwrap()
+            .starts_with(b"{"));
+        assert_eq!(
+            read_events(&app).unwrap(),
+            vec![plain.clone(), compressed.clone()]
+        );
+
+        // Turning compression off again only affects files written afterwards
+        app.config.compress = false;
+        compressed.update(&app).unwrap();
+        assert!(fs::read(event_path(&app, compressed.id))
+            .unwrap()
+            .starts_with(b"{"));
+        assert_eq!(read_events(&app).unwrap(), vec![plain, compressed]);
+    }
+
+    #[test]
+    fn stores_events_in_month_directories() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let mut app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let flat = Event::new(
+            "Flat",
+            None,
+            Utc.ymd(2022, 3, 15).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        flat.store(&app).unwrap();
+
+        app.config.layout = Layout::Monthly;
+        let april = Event::new(
+            "April",
+            None,
+            Utc.ymd(2022, 4, 30).and_hms(23, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        april.store(&app).unwrap();
+        assert!(app.events_dir.join("2022-04").join("1.json").is_file());
+        assert_eq!(Event::load(&app, april.id).unwrap(), april);
+        assert_eq!(count_events(&app).unwrap(), 2);
+
+        // Moving an event to another month moves its file
+        let may = move_event(&app, april.id, Utc.ymd(2022, 5, 2).and_hms(10, 0, 0)).unwrap();
+        assert!(!app.events_dir.join("2022-04").join("1.json").exists());
+        assert!(app.events_dir.join("2022-05").join("1.json").is_file());
+        assert_eq!(read_events(&app).unwrap(), vec![flat.clone(), may.clone()]);
+
+        let start = Utc.ymd(2022, 5, 1).and_hms(0, 0, 0);
+        let end = Utc.ymd(2022, 6, 1).and_hms(0, 0, 0);
+        assert_eq!(
+            read_events_in_range(&app, start, end, true).unwrap(),
+            vec![may.clone()]
+        );
+
+        assert_eq!(migrate_layout(&app).unwrap(), 1);
+        assert!(app.events_dir.join("2022-03").join("0.json").is_file());
+        assert!(!app.events_dir.join("2022-04").exists());
+        assert_eq!(migrate_layout(&app).unwrap(), 0);
+
+        app.config.layout = Layout::Flat;
+        assert_eq!(migrate_layout(&app).unwrap(), 2);
+        assert!(event_path(&app, flat.id).is_file());
+        assert!(event_path(&app, may.id).is_file());
+        assert!(month_dirs(&app).unwrap().is_empty());
+        assert_eq!(read_events(&app).unwrap(), vec![flat, may]);
+    }
+
+    #[test]
+    fn monthly_range_queries_include_events_from_earlier_months() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let mut app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+        app.config.layout = Layout::Monthly;
+
+        let conference = Event::new(
+            "Conference",
+            None,
+            Utc.ymd(2022, 1, 20).and_hms(9, 0, 0),
+            Some(Utc.ymd(2022, 3, 10).and_hms(17, 0, 0)),
+            &app,
+        )
+        .unwrap();
+        conference.store(&app).unwrap();
+        let april = Event::new(
+            "April",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        april.store(&app).unwrap();
+
+        // The conference is stored in January and ends in the middle of March
+        let march = (
+            Utc.ymd(2022, 3, 1).and_hms(0, 0, 0),
+            Utc.ymd(2022, 4, 1).and_hms(0, 0, 0),
+        );
+        assert_eq!(
+            read_events_in_range(&app, march.0, march.1, true).unwrap(),
+            vec![conference]
+        );
+    }
+
+    #[test]
+    fn converts_legacy_bincode_events_to_json() {
+        use chrono::TimeZone;
+
+        #[derive(Serialize)]
+        struct LegacyEvent<'a> {
+            name: &'a str,
+            description: Option<&'a str>,
+            date_time: DateTime<Utc>,
+            id: u128,
+        }
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let legacy = LegacyEvent {
+            name: "Legacy Event",
+            description: Some("Stored by evnt 0.1"),
+            date_time: Utc.ymd(2021, 6, 1).and_hms(8, 0, 0),
+            id: 42,
+        };
+        fs::write(
+            legacy_event_path(&app, 42),
+            bincode::serialize(&legacy).unwrap(),
+        )
+        .unwrap();
+
+        let events = read_events(&app).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].name, legacy.name);
+        assert_eq!(events[0].id(), 42);
+        assert!(!legacy_event_path(&app, 42).exists());
+        assert_eq!(Event::load(&app, 42).unwrap(), events[0]);
+    }
+
+    #[test]
+    fn calendars_are_separate() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        let work = app.calendar("work").unwrap();
+        let personal = app.calendar("personal").unwrap();
+
+        for app in [&app, &work, &personal] {
+            crate::utils::create_dirs(app).unwrap();
+        }
+
+        let event = Event::new(
+            "Meeting",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &work,
+        )
+        .unwrap();
+        event.store(&work).unwrap();
+
+        assert_eq!(read_events(&work).unwrap(), vec![event]);
+        assert!(read_events(&personal).unwrap().is_empty());
+        assert!(read_events(&app).unwrap().is_empty());
+    }
+
+    #[test]
+    fn concurrent_writers_get_unique_ids() {
+        use chrono::TimeZone;
+        use std::{collections::HashSet, sync::Arc, thread};
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = Arc::new(App::new(temp_data_dir.path()));
+        crate::utils::create_dirs(&app).unwrap();
+
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let app = Arc::clone(&app);
+
+                thread::spawn(move || {
+                    (0..100)
+                        .map(|n| {
+                            let event = Event::new(
+                                &n.to_string(),
+                                None,
+                                Utc.ymd(2022, 1, 1).and_hms(0, 0, 0),
+                                None,
+                                &app,
+                            )
+                            .unwrap();
+                            event.store(&app).unwrap();
+
+                            event.id
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let ids: HashSet<u128> = threads
+            .into_iter()
+            .flat_map(|thread| thread.join().unwrap())
+            .collect();
+
+        assert_eq!(ids.len(), 200);
+        assert_eq!(read_events(&app).unwrap().len(), 200);
+    }
+
+    #[test]
+    fn ids_continue_after_existing_events() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        fs::File::create(event_path(&app, 41)).unwrap();
+
+        assert_eq!(generate_id(&app).unwrap(), 42);
+        assert_eq!(generate_id(&app).unwrap(), 43);
+
+        fs::remove_file(app.events_dir.join(NEXT_ID_FILE)).unwrap();
+        assert_eq!(generate_id(&app).unwrap(), 42);
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let date_time = Utc.ymd(2022, 4, 4).and_hms(10, 0, 0);
+
+        for name in ["", "   ", &"a".repeat(MAX_NAME_LENGTH + 1)] {
+            assert!(matches!(
+                Event::new(name, None, date_time, None, &app),
+                Err(CalendarError::InvalidName(_))
+            ));
+        }
+
+        assert!(Event::new(&"a".repeat(MAX_NAME_LENGTH), None, date_time, None, &app).is_ok());
+    }
+
+    #[test]
+    fn preserves_timezones() {
+        use chrono::TimeZone;
+        use chrono_tz::America::New_York;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Meeting",
+            None,
+            New_York
+                .ymd(2022, 4, 4)
+                .and_hms(9, 0, 0)
+                .with_timezone(&Utc),
+            None,
+            &app,
+        )
+        .unwrap()
+        .with_timezone(New_York);
+        event.store(&app).unwrap();
+
+        let read_event = Event::load(&app, event.id()).unwrap();
+
+        assert_eq!(read_event.tz, New_York);
+        assert_eq!(
+            read_event.local_time(),
+            New_York.ymd(2022, 4, 4).and_hms(9, 0, 0)
+        );
+    }
+
+    #[test]
+    fn deletes_events_by_id() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2000, 2, 4).and_hms(20, 10, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
+
+        delete_event(&app, event.id()).unwrap();
+
+        assert!(read_events(&app).unwrap().is_empty());
+        assert!(matches!(
+            delete_event(&app, event.id()),
+            Err(CalendarError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn stores_notes_in_sidecar_files() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2000, 2, 4).and_hms(20, 10, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
+
+        assert_eq!(get_notes(&app, event.id()).unwrap(), None);
+        set_notes(&app, event.id(), "# Agenda\n\n- Budget\n").unwrap();
+        assert_eq!(
+            get_notes(&app, event.id()).unwrap().as_deref(),
+            Some("# Agenda\n\n- Budget\n")
+        );
+        assert_eq!(read_events(&app).unwrap(), vec![event.clone()]);
+        assert!(matches!(
+            set_notes(&app, 1000, "notes"),
+            Err(CalendarError::NotFound(1000))
+        ));
+
+        event.delete_file(&app).unwrap();
+
+        assert!(!notes_path(&app, event.id()).exists());
+    }
+
+    #[test]
+    fn restores_deleted_events_from_the_trash() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2000, 2, 4).and_hms(20, 10, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
+        set_notes(&app, event.id(), "Bring snacks").unwrap();
+
+        delete_event(&app, event.id()).unwrap();
+        assert!(read_events(&app).unwrap().is_empty());
+        assert!(get_ids(&app).unwrap().is_empty());
+
+        assert_eq!(restore_event(&app, event.id()).unwrap(), event);
+        assert_eq!(read_events(&app).unwrap(), vec![event.clone()]);
+        assert_eq!(
+            get_notes(&app, event.id()).unwrap().as_deref(),
+            Some("Bring snacks")
+        );
+        assert!(matches!(
+            restore_event(&app, event.id()),
+            Err(CalendarError::NotFound(_))
+        ));
+
+        delete_event(&app, event.id()).unwrap();
+        assert_eq!(empty_trash(&app).unwrap(), 1);
+        assert_eq!(empty_trash(&app).unwrap(), 0);
+        assert!(matches!(
+            restore_event(&app, event.id()),
+            Err(CalendarError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn deletes_events_matching_a_predicate() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let cutoff = Utc.ymd(2022, 4, 4).and_hms(0, 0, 0);
+        for day in 1..=5 {
+            Event::new(
+                "Event Name",
+                None,
+                Utc.ymd(2022, 4, day).and_hms(10, 0, 0),
+                None,
+                &app,
+            )
+            .unwrap()
+            .store(&app)
+            .unwrap();
+        }
+
+        assert_eq!(
+            delete_where(&app, |event| event.date_time < cutoff).unwrap(),
+            3
+        );
+        assert!(read_events(&app)
+            .unwrap()
+            .iter()
+            .all(|event| event.date_time >= cutoff));
+        assert_eq!(delete_where(&app, |_| false).unwrap(), 0);
+
+        // Trashed events can be restored like ones deleted with delete_event
+        let restored = restore_event(&app, 0).unwrap();
+        assert_eq!(restored.date_time, Utc.ymd(2022, 4, 1).and_hms(10, 0, 0));
+    }
+
+    #[test]
+    fn pads_ids_in_file_names() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let mut app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let mut old = Event::new(
+            "Old",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        old.store(&app).unwrap();
+
+        app.config.id_width = 4;
+        let new = Event::new(
+            "New",
+            None,
+            Utc.ymd(2022, 4, 5).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        new.store(&app).unwrap();
+
+        assert!(app.events_dir.join("0.json").is_file());
+        assert!(app.events_dir.join("0001.json").is_file());
+        assert_eq!(get_ids(&app).unwrap().len(), 2);
+        assert_eq!(Event::load(&app, old.id()).unwrap(), old);
+
+        // Writing an unpadded file again renames it
+        old.name = "Renamed".to_string();
+        old.update(&app).unwrap();
+        assert!(!app.events_dir.join("0.json").exists());
+        assert!(app.events_dir.join("0000.json").is_file());
+        assert_eq!(read_events(&app).unwrap().len(), 2);
+
+        assert_eq!(event_id(Path::new("0042.json")), Some(42));
+        assert_eq!(event_id(Path::new("0000.json")), Some(0));
+        assert_eq!(event_id(Path::new("0042")), None);
+        assert_eq!(event_id(Path::new("+42.json")), None);
+    }
+
+    #[test]
+    fn finds_events_after_the_id_width_changes() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let mut app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+        app.config.id_width = 4;
+
+        let mut event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
+        assert!(app.events_dir.join("0000.json").is_file());
 
-    /// The time at which the event occurs (stored in UTC, timezone offset is added when needed)
-    pub date_time: DateTime<Utc>,
+        app.config.id_width = 6;
+        assert_eq!(Event::load(&app, event.id()).unwrap(), event);
+        assert!(event_exists(&app, event.id()));
 
-    /// Unique id for the event. This is necessary because different events can have the same name. Also acts as the filename for the serialized event
-    id: u128,
-}
+        event.store(&app).unwrap();
+        assert_eq!(read_events(&app).unwrap(), vec![event.clone()]);
+        assert_eq!(count_events(&app).unwrap(), 1);
+        assert!(app.events_dir.join("000000.json").is_file());
+        assert!(!app.events_dir.join("0000.json").exists());
 
-impl Event {
-    pub fn new(
-        name: &str,
-        description: Option<&str>,
-        date_time: DateTime<Utc>,
-        app: &App,
-    ) -> Result<Self> {
-        Ok(Self {
-            name: name.to_string(),
-            description: description.map(String::from),
-            date_time,
+        app.config.id_width = 2;
+        event.name = "Renamed".to_string();
+        event.update(&app).unwrap();
+        assert_eq!(read_events(&app).unwrap(), vec![event.clone()]);
+        assert!(app.events_dir.join("00.json").is_file());
 
-            id: generate_id(app)
-                .with_context(|| format!("failed to generate event id for `{}`", name))?,
-        })
+        app.config.id_width = 3;
+        delete_event(&app, event.id()).unwrap();
+        assert!(read_events(&app).unwrap().is_empty());
+        assert_eq!(restore_event(&app, event.id()).unwrap(), event);
     }
 
-    /// Serializes and writes the event to the filesystem (using [bincode]). The event gets written to [App::events_dir].
-    /// The filename is equal to the unique id of the event
-    pub fn store(&self, app: &App) -> Result<()> {
-        let bytes = bincode::serialize(self).with_context(|| {
-            format!(
-                "failed to serialize event `{}` (id: {})",
-                self.name, self.id
-            )
-        })?;
+    #[test]
+    fn lenient_reading_skips_corrupt_files() {
+        use chrono::TimeZone;
 
-        let path = app.events_dir.join(self.id.to_string());
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
 
-        fs::write(&path, bytes).with_context(|| {
-            format!(
-                "failed to write event `{}` (id: {}) to `{}`",
-                self.name,
-                self.id,
-                path.to_string_lossy()
-            )
-        })?;
+        let event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2000, 2, 4).and_hms(20, 10, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
 
-        Ok(())
+        let corrupt_path = event_path(&app, 1000);
+        fs::write(&corrupt_path, "{ not json").unwrap();
+
+        assert!(read_events(&app).is_err());
+
+        let (events, failures) = read_events_lenient(&app).unwrap();
+
+        assert_eq!(events, vec![event]);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, corrupt_path);
+        assert!(matches!(failures[0].1, CalendarError::Serialization { .. }));
     }
 
-    /// Deletes the file associated with the event
-    pub fn delete_file(&self, app: &App) -> Result<()> {
-        fs::remove_file(app.events_dir.join(self.id.to_string()))
-            .with_context(|| format!("failed to delete task `{}` (id: {})", self.name, self.id))?;
+    #[test]
+    fn ignores_files_that_arent_events() {
+        use chrono::TimeZone;
 
-        Ok(())
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2000, 2, 4).and_hms(20, 10, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
+
+        for name in [
+            "README.txt",
+            ".DS_Store",
+            "notes",
+            "5.txt",
+            "+5.json",
+            "-5.json",
+        ] {
+            fs::write(app.events_dir.join(name), "not an event").unwrap();
+        }
+
+        assert_eq!(read_events(&app).unwrap(), vec![event.clone()]);
+        assert_eq!(get_ids(&app).unwrap(), vec![event.id]);
     }
-}
 
-/// Reads all the events from [App::events_dir]
-pub fn read_events(app: &App) -> Result<Vec<Event>> {
-    let mut events = Vec::new();
+    #[test]
+    fn stores_many_events_at_once() {
+        use chrono::TimeZone;
 
-    for entry in fs::read_dir(&app.events_dir).with_context(|| {
-        format!(
-            "failed to read directory `{}`",
-            app.events_dir.to_string_lossy()
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let existing = Event::new(
+            "Existing",
+            None,
+            Utc.ymd(2022, 1, 1).and_hms(12, 0, 0),
+            None,
+            &app,
         )
-    })? {
-        let entry = entry.with_context(|| {
-            format!(
-                "failed to get directory entry from `{}`",
-                app.events_dir.to_string_lossy()
-            )
-        })?;
+        .unwrap();
+        existing.store(&app).unwrap();
 
-        if entry
-            .file_type()
-            .with_context(|| {
-                format!(
-                    "failed to get file type from file `{}`",
-                    entry.file_name().to_string_lossy()
-                )
-            })?
-            .is_file()
-        {
-            let bytes = fs::read(entry.path()).with_context(|| {
-                format!(
-                    "failed to read from file `{}`",
-                    entry.file_name().to_string_lossy()
-                )
-            })?;
+        let draft = |name: &str| EventDraft {
+            name: name.to_string(),
+            description: None,
+            date_time: Utc.ymd(2022, 1, 2).and_hms(12, 0, 0),
+            end_time: None,
+        };
+        let drafts: Vec<EventDraft> = (0..500).map(|i| draft(&i.to_string())).collect();
 
-            events.push(bincode::deserialize(&bytes).with_context(|| {
-                format!(
-                    "failed to deserialize event from file `{}`",
-                    entry.file_name().to_string_lossy()
-                )
-            })?);
-        }
+        let events = store_many(&app, drafts).unwrap();
+        assert_eq!(events.len(), 500);
+        assert_eq!(events[0].name, "0");
+
+        let mut ids: Vec<u128> = events.iter().map(|event| event.id).collect();
+        ids.push(existing.id);
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), 501);
+        assert_eq!(read_events(&app).unwrap().len(), 501);
+        assert!(!ids.contains(&generate_id(&app).unwrap()));
+
+        // Nothing is stored if one of the drafts is invalid
+        assert!(store_many(&app, vec![draft("Valid"), draft("")]).is_err());
+        assert_eq!(read_events(&app).unwrap().len(), 501);
     }
 
-    Ok(events)
-}
+    #[test]
+    fn clears_all_events() {
+        use chrono::TimeZone;
 
-/// Generates a unique id for an event
-fn generate_id(app: &App) -> Result<u128> {
-    let ids = get_ids(app).with_context(|| "failed to get event id's".to_string())?;
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
 
-    loop {
-        let id = rand::random();
+        let mut ids = Vec::new();
 
-        if !ids.contains(&id) {
-            return Ok(id);
+        for day in 1..=3 {
+            let event = Event::new(
+                "Event Name",
+                None,
+                Utc.ymd(2022, 1, day).and_hms(12, 0, 0),
+                None,
+                &app,
+            )
+            .unwrap();
+            event.store(&app).unwrap();
+            ids.push(event.id);
         }
+        fs::write(app.events_dir.join("README.txt"), "not an event").unwrap();
+        set_notes(&app, ids[0], "Some notes").unwrap();
+
+        assert_eq!(clear_all(&app).unwrap(), 3);
+        assert!(read_events(&app).unwrap().is_empty());
+        assert!(!notes_path(&app, ids[0]).exists());
+        assert!(app.events_dir.join("README.txt").is_file());
+        assert!(!ids.contains(&generate_id(&app).unwrap()));
+        assert_eq!(clear_all(&app).unwrap(), 0);
     }
-}
 
-/// Gets all event ids by reading filenames from [App::events_dir]
-fn get_ids(app: &App) -> Result<Vec<u128>> {
-    let mut ids = Vec::new();
+    #[test]
+    fn validates_colors() {
+        use chrono::TimeZone;
 
-    for entry in fs::read_dir(&app.events_dir).with_context(|| {
-        format!(
-            "failed to read directory `{}`",
-            app.events_dir.to_string_lossy()
-        )
-    })? {
-        let entry = entry.with_context(|| {
-            format!(
-                "failed to get directory entry from `{}`",
-                app.events_dir.to_string_lossy()
-            )
-        })?;
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
 
-        let name = entry.file_name();
-        let name = name.to_string_lossy();
+        let event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2000, 2, 4).and_hms(20, 10, 0),
+            None,
+            &app,
+        )
+        .unwrap();
 
-        if let Ok(id) = name.parse::<u128>() {
-            ids.push(id);
+        for color in ["#1E90FF", "#1e90ff", "Red", "gray"] {
+            assert!(event.clone().with_color(color).is_ok());
+        }
+        for color in ["", "#1E90F", "#1E90FG", "1E90FF", "magenta"] {
+            assert!(matches!(
+                event.clone().with_color(color),
+                Err(CalendarError::InvalidEvent(_))
+            ));
         }
-    }
 
-    Ok(ids)
-}
+        let event = event.with_color("#1E90FF").unwrap();
+        event.store(&app).unwrap();
+        assert_eq!(Event::load(&app, event.id).unwrap(), event);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let mut invalid = event;
+        invalid.color = Some("not a color".to_string());
+        assert!(invalid.store(&app).is_err());
+    }
 
     #[test]
-    fn generates_unique_ids() {
+    fn validates_urls() {
+        use chrono::TimeZone;
+
         let temp_data_dir = assert_fs::TempDir::new().unwrap();
         let app = App::new(temp_data_dir.path());
         crate::utils::create_dirs(&app).unwrap();
 
-        let mut ids = Vec::new();
+        let event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2000, 2, 4).and_hms(20, 10, 0),
+            None,
+            &app,
+        )
+        .unwrap();
 
-        for _ in 0..1000 {
-            let id = generate_id(&app).unwrap();
-            assert!(!ids.contains(&id));
+        for url in [
+            "https://meet.example.com/abc-def",
+            "mailto:alice@example.com",
+        ] {
+            assert!(event.clone().with_url(url).is_ok());
+        }
+        for url in ["", "example.com/call", "https://exa mple.com"] {
+            let error = event.clone().with_url(url).unwrap_err();
+            assert!(error
+                .to_string()
+                .contains(&format!("invalid url `{}`", url)));
+        }
 
-            ids.push(id);
+        let event = event.with_url("https://meet.example.com/abc-def").unwrap();
+        event.store(&app).unwrap();
+        assert_eq!(Event::load(&app, event.id).unwrap(), event);
 
-            fs::File::create(app.data_dir.join(id.to_string())).unwrap();
-        }
+        let mut invalid = event;
+        invalid.url = Some("not a url".to_string());
+        assert!(invalid.store(&app).is_err());
     }
 
     #[test]
-    fn event_serializes_and_deserializes() {
+    fn all_day_events_cover_whole_local_days() {
         use chrono::TimeZone;
+        use chrono_tz::Europe::Berlin;
 
         let temp_data_dir = assert_fs::TempDir::new().unwrap();
         let app = App::new(temp_data_dir.path());
         crate::utils::create_dirs(&app).unwrap();
 
         let event = Event::new(
-            "Test Event",
-            Some("Event description"),
-            Utc.ymd(1000, 10, 10).and_hms(14, 30, 0),
+            "Birthday",
+            None,
+            Berlin.ymd(2022, 7, 4).and_hms(15, 0, 0).with_timezone(&Utc),
+            None,
             &app,
         )
-        .unwrap();
+        .unwrap()
+        .with_timezone(Berlin)
+        .with_all_day(true);
         event.store(&app).unwrap();
 
-        let events = read_events(&app).unwrap();
-        let read_event = events.get(0).unwrap();
+        assert_eq!(event.start(), Utc.ymd(2022, 7, 3).and_hms(22, 0, 0));
+        assert_eq!(event.end(), Utc.ymd(2022, 7, 4).and_hms(22, 0, 0));
 
-        assert!(event == *read_event);
+        // Morning of the same day, before the time stored in date_time
+        let morning = read_events_in_range(
+            &app,
+            Utc.ymd(2022, 7, 4).and_hms(6, 0, 0),
+            Utc.ymd(2022, 7, 4).and_hms(7, 0, 0),
+            true,
+        )
+        .unwrap();
+        assert_eq!(morning, vec![event]);
+
+        let next_day = read_events_in_range(
+            &app,
+            Utc.ymd(2022, 7, 4).and_hms(22, 0, 0),
+            Utc.ymd(2022, 7, 5).and_hms(0, 0, 0),
+            true,
+        )
+        .unwrap();
+        assert!(next_day.is_empty());
     }
 
     #[test]
-    fn serializes_and_deserializes_lots_of_events() {
+    fn range_queries_include_multi_day_events() {
         use chrono::TimeZone;
 
         let temp_data_dir = assert_fs::TempDir::new().unwrap();
         let app = App::new(temp_data_dir.path());
         crate::utils::create_dirs(&app).unwrap();
 
-        let mut original_events = Vec::new();
+        let conference = Event::new(
+            "Conference",
+            None,
+            Utc.ymd(2022, 6, 1).and_hms(9, 0, 0),
+            Some(Utc.ymd(2022, 6, 3).and_hms(17, 0, 0)),
+            &app,
+        )
+        .unwrap();
+        conference.store(&app).unwrap();
+        let point = Event::new(
+            "Point",
+            None,
+            Utc.ymd(2022, 6, 3).and_hms(0, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        point.store(&app).unwrap();
 
-        for n in 0..100 {
+        let middle_day = read_events_in_range(
+            &app,
+            Utc.ymd(2022, 6, 2).and_hms(0, 0, 0),
+            Utc.ymd(2022, 6, 3).and_hms(0, 0, 0),
+            true,
+        )
+        .unwrap();
+        assert_eq!(middle_day, vec![conference.clone()]);
+
+        let after_end = read_events_in_range(
+            &app,
+            Utc.ymd(2022, 6, 3).and_hms(17, 0, 0),
+            Utc.ymd(2022, 6, 4).and_hms(0, 0, 0),
+            true,
+        )
+        .unwrap();
+        assert!(after_end.is_empty());
+    }
+
+    #[test]
+    fn supports_dates_within_supported_years() {
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let first = Event::new(
+            "First",
+            None,
+            Utc.ymd(MIN_YEAR, 1, 1).and_hms(0, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        let last = Event::new(
+            "Last",
+            None,
+            Utc.ymd(MAX_YEAR, 12, 31).and_hms(23, 59, 59),
+            None,
+            &app,
+        )
+        .unwrap();
+
+        for event in [&first, &last] {
+            event.store(&app).unwrap();
+            assert_eq!(&Event::load(&app, event.id()).unwrap(), event);
+            assert_eq!(&Event::from_json(&event.to_json().unwrap()).unwrap(), event);
+        }
+        let ics = crate::ics::export_ics(&[first.clone(), last], app.now()).unwrap();
+        assert!(ics.contains("DTSTART:00010101T000000Z\r\n"));
+        assert!(ics.contains("DTSTART:99991231T235959Z\r\n"));
+
+        for date_time in [
+            Utc.ymd(MIN_YEAR - 1, 12, 31).and_hms(23, 59, 59),
+            Utc.ymd(MAX_YEAR + 1, 1, 1).and_hms(0, 0, 0),
+        ] {
+            assert!(matches!(
+                Event::new("Outside", None, date_time, None, &app),
+                Err(CalendarError::InvalidEvent(_))
+            ));
+        }
+
+        // The local time has to be within the supported years too
+        let mut outside = first.with_timezone(chrono_tz::America::New_York);
+        assert!(outside.validate().is_err());
+        assert!(crate::ics::export_ics(&[outside.clone()], app.now()).is_err());
+        assert!(outside.to_json().is_err());
+
+        // All-day events end at the beginning of the next day
+        outside = Event::unsaved(
+            "All day",
+            None,
+            Utc.ymd(MAX_YEAR, 12, 31).and_hms(12, 0, 0),
+            None,
+            app.now(),
+        )
+        .unwrap()
+        .with_all_day(true);
+        assert!(outside.validate().is_err());
+    }
+
+    #[test]
+    fn reads_events_modified_since() {
+        use std::time::Duration;
+
+        use chrono::TimeZone;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = |name| {
             let event = Event::new(
-                &n.to_string(),
+                name,
+                None,
+                Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
                 None,
-                Utc.ymd(2000, 10, 10).and_hms(15, 15, 0),
                 &app,
             )
             .unwrap();
-
             event.store(&app).unwrap();
+            event
+        };
+        let since = SystemTime::now() - Duration::from_secs(60);
+        let mut old = event("Old");
+        let new = event("New");
+        fs::File::options()
+            .write(true)
+            .open(event_path(&app, old.id))
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH)
+            .unwrap();
 
-            original_events.push(event);
-        }
+        assert_eq!(
+            read_events_modified_since(&app, since).unwrap(),
+            vec![new.clone()]
+        );
+        assert_eq!(
+            read_events_modified_since(&app, SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .len(),
+            2
+        );
 
-        let read_events = read_events(&app).unwrap();
+        // Deleted events aren't reported
+        new.delete_file(&app).unwrap();
+        assert!(read_events_modified_since(&app, since).unwrap().is_empty());
 
-        for event in original_events {
-            assert!(read_events.contains(&event));
-        }
+        old.update(&app).unwrap();
+        assert_eq!(read_events_modified_since(&app, since).unwrap(), vec![old]);
     }
 
     #[test]
-    fn deletes_events() {
+    fn tells_past_from_future_events() {
+        use chrono::TimeZone;
+
+        let at = |hour| Utc.ymd(2022, 4, 4).and_hms(hour, 0, 0);
+        let meeting = Event::unsaved("Meeting", None, at(10), Some(at(12)), at(0)).unwrap();
+        let call = Event::unsaved("Call", None, at(10), None, at(0)).unwrap();
+
+        assert!(meeting.is_future(at(9)) && !meeting.is_past(at(9)));
+        assert!(!meeting.is_future(at(11)) && !meeting.is_past(at(11)));
+        assert!(meeting.is_past(at(12)));
+        assert!(!call.is_future(at(10)) && !call.is_past(at(10)));
+        assert!(call.is_past(at(11)));
+    }
+
+    #[test]
+    fn checks_overlaps() {
         use chrono::TimeZone;
 
         let temp_data_dir = assert_fs::TempDir::new().unwrap();
         let app = App::new(temp_data_dir.path());
         crate::utils::create_dirs(&app).unwrap();
 
-        let event = Event::new(
-            "Event Name",
-            Some("Event description"),
-            Utc.ymd(2000, 2, 4).and_hms(20, 10, 0),
-            &app,
-        )
-        .unwrap();
+        let start = Utc.ymd(2022, 4, 4).and_hms(10, 0, 0);
+        let event = |offset: i64, length: i64| {
+            let date_time = start + Duration::minutes(offset);
+            Event::new(
+                "Event Name",
+                None,
+                date_time,
+                Some(date_time + Duration::minutes(length)),
+                &app,
+            )
+            .unwrap()
+        };
 
-        event.store(&app).unwrap();
-        assert!(app.events_dir.join(event.id.to_string()).exists());
+        let meeting = event(0, 60);
+        assert!(meeting.overlaps(&event(30, 60)));
+        assert!(event(30, 60).overlaps(&meeting));
+        assert!(meeting.overlaps(&event(10, 10)));
+        // Touching events don't overlap
+        assert!(!meeting.overlaps(&event(60, 30)));
+        assert!(!meeting.overlaps(&event(-30, 30)));
 
-        event.delete_file(&app).unwrap();
-        assert!(!app.events_dir.join(event.id.to_string()).exists());
+        // Instantaneous events
+        assert!(meeting.overlaps(&event(0, 0)));
+        assert!(!meeting.overlaps(&event(60, 0)));
+        assert!(event(90, 0).overlaps(&event(90, 0)));
+        assert!(!event(90, 0).overlaps(&event(91, 0)));
     }
 }
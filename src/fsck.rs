@@ -0,0 +1,248 @@
+//! Checking (and repairing) the files in the events directory
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::{Context, Result},
+    event, App,
+};
+
+/// The name of the directory in [App::events_dir] that unreadable files are moved to when repairing (see [fsck])
+pub const CORRUPT_DIR: &str = ".corrupt";
+
+/// A problem with a file in [App::events_dir]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The file is named like an event file, but is empty
+    Empty(PathBuf),
+    /// The file isn't named like an event file, so it's ignored when reading events
+    NotAnEvent(PathBuf),
+    /// The file is named like an event file, but can't be read or deserialized
+    Unreadable { path: PathBuf, error: String },
+    /// The id stored in the file doesn't match the id in its name
+    IdMismatch { path: PathBuf, id: u128 },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::Empty(path) => write!(f, "`{}` is empty", path.to_string_lossy()),
+            Problem::NotAnEvent(path) => {
+                write!(f, "`{}` is not an event file", path.to_string_lossy())
+            }
+            Problem::Unreadable { path, error } => {
+                write!(f, "`{}` can't be read: {}", path.to_string_lossy(), error)
+            }
+            Problem::IdMismatch { path, id } => write!(
+                f,
+                "`{}` contains the event with id {}",
+                path.to_string_lossy(),
+                id
+            ),
+        }
+    }
+}
+
+/// The result of [fsck]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FsckReport {
+    /// All problems that were found
+    pub problems: Vec<Problem>,
+    /// The files that were moved while repairing, as `(from, to)` pairs (empty if nothing was repaired)
+    pub moved: Vec<(PathBuf, PathBuf)>,
+}
+
+/// Checks all files in [App::events_dir] and its month directories (see [event::Layout::Monthly]) for problems (see
+/// [Problem]). With `repair`, files whose id doesn't match their name are renamed to match the id (or moved to
+/// [CORRUPT_DIR] if there already is an event with that id) and empty or unreadable files are moved to [CORRUPT_DIR].
+/// Files that aren't named like events are only reported. Hidden files and directories (like named calendars) are
+/// skipped
+pub fn fsck(app: &App, repair: bool) -> Result<FsckReport> {
+    // Only checking doesn't write anything, so it doesn't need the lock in read-only mode
+    let _lock = (repair || !app.read_only)
+        .then(|| event::lock(app))
+        .transpose()?;
+    let mut report = FsckReport::default();
+
+    let mut paths = Vec::new();
+    let mut dirs = vec![app.events_dir.clone()];
+    dirs.extend(event::month_dirs(app)?.into_iter().map(|(_, dir)| dir));
+
+    for dir in dirs {
+        for entry in fs::read_dir(&dir)
+            .with_context(|| format!("failed to read directory `{}`", dir.to_string_lossy()))?
+        {
+            let entry = entry.with_context(|| {
+                format!(
+                    "failed to get directory entry from `{}`",
+                    dir.to_string_lossy()
+                )
+            })?;
+            // Symlinks are followed like when reading events, dangling ones are left alone
+            let is_file =
+                event::entry_file_type(&entry)?.is_some_and(|file_type| file_type.is_file());
+
+            if is_file
+                && !entry.file_name().to_string_lossy().starts_with('.')
+                && !event::is_notes_file(&entry.path())
+            {
+                paths.push(entry.path());
+            }
+        }
+    }
+    paths.sort();
+
+    for path in paths {
+        let name_id = match event::event_id(&path) {
+            Some(id) => id,
+            None => {
+                report.problems.push(Problem::NotAnEvent(path));
+                continue;
+            }
+        };
+
+        let len = fs::metadata(&path)
+            .with_context(|| format!("failed to read metadata of `{}`", path.to_string_lossy()))?
+            .len();
+
+        if len == 0 {
+            report.problems.push(Problem::Empty(path.clone()));
+
+            if repair {
+                report.moved.push(quarantine(app, &path)?);
+            }
+            continue;
+        }
+
+        let event = match event::parse_event_file(app, &path) {
+            Ok(event) => event,
+            Err(e) => {
+                report.problems.push(Problem::Unreadable {
+                    path: path.clone(),
+                    error: e.to_string(),
+                });
+
+                if repair {
+                    report.moved.push(quarantine(app, &path)?);
+                }
+                continue;
+            }
+        };
+
+        if event.id() != name_id {
+            report.problems.push(Problem::IdMismatch {
+                path: path.clone(),
+                id: event.id(),
+            });
+
+            if repair {
+                // Keep the file in its directory, only fix the name
+                let target = event::renamed_event_file(app, &path, event.id());
+
+                if event::event_exists(app, event.id()) {
+                    report.moved.push(quarantine(app, &path)?);
+                } else {
+                    rename(&path, &target)?;
+                    report.moved.push((path, target));
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Moves the file at `path` into [CORRUPT_DIR], adding a number to its name if a file with the same name is already
+/// there. Returns where the file was moved from and to
+fn quarantine(app: &App, path: &Path) -> Result<(PathBuf, PathBuf)> {
+    let dir = app.events_dir.join(CORRUPT_DIR);
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("failed to create directory `{}`", dir.to_string_lossy()))?;
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut target = dir.join(name.as_ref());
+    let mut n = 1;
+
+    while target.exists() {
+        target = dir.join(format!("{}.{}", name, n));
+        n += 1;
+    }
+
+    rename(path, &target)?;
+
+    Ok((path.to_path_buf(), target))
+}
+
+/// Renames the file at `from` to `to`
+fn rename(from: &Path, to: &Path) -> Result<()> {
+    fs::rename(from, to).with_context(|| {
+        format!(
+            "failed to rename `{}` to `{}`",
+            from.to_string_lossy(),
+            to.to_string_lossy()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::event::Event;
+
+    #[test]
+    fn finds_and_repairs_problems() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
+
+        // A copy of the event under the wrong id, and one whose id is free
+        let wrong_name = event::event_path(&app, 100);
+        fs::copy(event::event_path(&app, event.id()), &wrong_name).unwrap();
+        let mut moved_event = event.clone();
+        moved_event.set_id(50);
+        let misplaced = event::event_path(&app, 51);
+        fs::write(&misplaced, serde_json::to_vec(&moved_event).unwrap()).unwrap();
+
+        let empty = event::event_path(&app, 200);
+        fs::write(&empty, "").unwrap();
+        let corrupt = event::event_path(&app, 300);
+        fs::write(&corrupt, "{ not json").unwrap();
+        let junk = app.events_dir.join("README.txt");
+        fs::write(&junk, "not an event").unwrap();
+
+        let report = fsck(&app, false).unwrap();
+        assert_eq!(report.problems.len(), 5);
+        assert!(report.problems.contains(&Problem::Empty(empty.clone())));
+        assert!(report.problems.contains(&Problem::NotAnEvent(junk.clone())));
+        assert!(report.problems.contains(&Problem::IdMismatch {
+            path: wrong_name.clone(),
+            id: event.id()
+        }));
+        assert!(report.moved.is_empty());
+
+        let report = fsck(&app, true).unwrap();
+        assert_eq!(report.problems.len(), 5);
+        assert_eq!(report.moved.len(), 4);
+        assert!(app.events_dir.join(CORRUPT_DIR).join("200.json").is_file());
+        assert!(app.events_dir.join(CORRUPT_DIR).join("300.json").is_file());
+        assert!(junk.is_file());
+
+        assert_eq!(event::read_events(&app).unwrap(), vec![event, moved_event]);
+        assert_eq!(fsck(&app, false).unwrap().problems.len(), 1);
+    }
+}
@@ -0,0 +1,1133 @@
+//! Conversion of events from and to the iCalendar format ([RFC 5545](https://datatracker.ietf.org/doc/html/rfc5545))
+
+use std::{collections::HashMap, io::Write};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+
+use crate::{
+    error::{Context, Result},
+    event::{self, Event},
+    import::{self, Diagnostic, DuplicateKey, DuplicatePolicy, ImportPreview},
+    recurrence::{Frequency, Recurrence},
+    App,
+};
+
+/// The maximum length of a content line in octets (excluding the line break)
+const MAX_LINE_LENGTH: usize = 75;
+
+/// Exports the events as an iCalendar `VCALENDAR` containing one `VEVENT` per event (see [export_ics_to])
+pub fn export_ics(events: &[Event], now: DateTime<Utc>) -> Result<String> {
+    let mut ics = Vec::new();
+    export_ics_to(events.iter().cloned(), now, &mut ics)?;
+
+    Ok(String::from_utf8(ics).expect("iCalendar files are valid UTF-8"))
+}
+
+/// Writes the events to `out` as an iCalendar `VCALENDAR` containing one `VEVENT` per event. Each `VEVENT` is written
+/// as soon as its event is yielded, so together with [event::iter_events] a whole calendar can be exported without
+/// holding all events in memory. The `UID` of each event is derived from its id, so exporting the same event twice
+/// results in the same `UID`. `now` is the time of the export (usually [App::now]), written as the `DTSTAMP` of every
+/// `VEVENT`. Fails if an event is outside of the supported years (see [event::MIN_YEAR] and [event::MAX_YEAR]) instead
+/// of writing malformed dates, the events before it have been written already then
+pub fn export_ics_to<W: Write>(
+    events: impl Iterator<Item = Event>,
+    now: DateTime<Utc>,
+    out: &mut W,
+) -> Result<()> {
+    let mut ics = String::new();
+    let mut write = |ics: &mut String| {
+        let result = out
+            .write_all(ics.as_bytes())
+            .with_context(|| "failed to write iCalendar file");
+        ics.clear();
+        result
+    };
+
+    push_line(&mut ics, "BEGIN:VCALENDAR");
+    push_line(&mut ics, "VERSION:2.0");
+    push_line(&mut ics, "PRODID:-//evnt//evnt//EN");
+    write(&mut ics)?;
+
+    for event in events {
+        push_vevent(&mut ics, &event, now)?;
+        write(&mut ics)?;
+    }
+
+    push_line(&mut ics, "END:VCALENDAR");
+    write(&mut ics)
+}
+
+/// Appends the `VEVENT` for the event to `ics`, with `now` as its `DTSTAMP`
+fn push_vevent(ics: &mut String, event: &Event, now: DateTime<Utc>) -> Result<()> {
+    event.validate_years()?;
+
+    push_line(ics, "BEGIN:VEVENT");
+    push_line(ics, &format!("UID:{}@evnt", event.id()));
+    push_line(ics, &format!("DTSTAMP:{}", format_date_time(now)));
+
+    // Events stored before the timestamps were recorded have the Unix epoch as placeholder
+    if event.created_at().timestamp() != 0 {
+        push_line(
+            ics,
+            &format!("CREATED:{}", format_date_time(event.created_at())),
+        );
+        push_line(
+            ics,
+            &format!("LAST-MODIFIED:{}", format_date_time(event.updated_at())),
+        );
+    }
+
+    if event.all_day {
+        // All-day events are exported as dates, DTEND is the (exclusive) day after the last day
+        push_line(
+            ics,
+            &format!("DTSTART;VALUE=DATE:{}", format_date(event.local_time())),
+        );
+
+        if event.end_time.is_some() {
+            let end = event.end().with_timezone(&event.tz);
+            push_line(ics, &format!("DTEND;VALUE=DATE:{}", format_date(end)));
+        }
+    } else {
+        push_line(ics, &time_property("DTSTART", event, event.date_time));
+
+        if let Some(end_time) = event.end_time {
+            push_line(ics, &time_property("DTEND", event, end_time));
+        }
+    }
+
+    if let Some(recurrence) = &event.recurrence {
+        push_line(
+            ics,
+            &format!(
+                "RRULE:{}",
+                format_rrule(recurrence, event.all_day, event.tz)
+            ),
+        );
+
+        for exception in &recurrence.exceptions {
+            if event.all_day {
+                let date = format_date(exception.with_timezone(&event.tz));
+                push_line(ics, &format!("EXDATE;VALUE=DATE:{}", date));
+            } else {
+                push_line(ics, &time_property("EXDATE", event, *exception));
+            }
+        }
+    }
+
+    push_line(ics, &format!("SUMMARY:{}", escape(&event.name)));
+
+    if let Some(description) = &event.description {
+        push_line(ics, &format!("DESCRIPTION:{}", escape(description)));
+    }
+
+    if let Some(location) = &event.location {
+        push_line(ics, &format!("LOCATION:{}", escape(location)));
+    }
+
+    for attendee in &event.attendees {
+        push_line(ics, &format_attendee(attendee));
+    }
+
+    // URL is a URI, not text, so it isn't escaped
+    if let Some(url) = &event.url {
+        push_line(ics, &format!("URL:{}", url));
+    }
+
+    // COLOR only allows CSS color names (RFC 7986), hex codes are exported as the property Apple uses instead
+    if let Some(color) = &event.color {
+        if color.starts_with('#') {
+            push_line(ics, &format!("X-APPLE-CALENDAR-COLOR:{}", color));
+        } else {
+            push_line(ics, &format!("COLOR:{}", color));
+        }
+    }
+
+    push_line(ics, "END:VEVENT");
+
+    Ok(())
+}
+
+/// Imports all `VEVENT`s of an iCalendar file as new events (with newly generated ids) and stores them. Events that
+/// are duplicates of existing events are handled according to `duplicates`. Returns the created (and overwritten)
+/// events.
+///
+/// `SUMMARY`, `DESCRIPTION`, `LOCATION`, `ATTENDEE`s (see [format_attendee]), `DTSTART`, `DTEND`, the color (`COLOR` or
+/// `X-APPLE-CALENDAR-COLOR`, if it's valid, see [event::validate_color]) and `URL` (if it's valid, see
+/// [event::validate_url]) are imported. Times with a `TZID` parameter are converted from that timezone to UTC, times
+/// without a `Z` suffix or `TZID` ("floating" times) are interpreted as UTC. `VEVENT`s that are malformed (e.g. missing
+/// `DTSTART` or with an unparsable time) are skipped
+pub fn import_ics(app: &App, ics: &str, duplicates: DuplicatePolicy) -> Result<Vec<Event>> {
+    // The ids and creation times of the existing events, overwritten events keep both
+    let mut existing: HashMap<DuplicateKey, (u128, DateTime<Utc>)> = match duplicates {
+        DuplicatePolicy::Allow => HashMap::new(),
+        _ => event::read_events(app)?
+            .iter()
+            .map(|event| {
+                (
+                    import::duplicate_key(event),
+                    (event.id(), event.created_at()),
+                )
+            })
+            .collect(),
+    };
+    let mut events = Vec::new();
+
+    for mut event in validate_ics(app, ics).events {
+        match (duplicates, existing.get(&import::duplicate_key(&event))) {
+            (DuplicatePolicy::Skip, Some(_)) => continue,
+            (DuplicatePolicy::Overwrite, Some(&(id, created_at))) => {
+                event.set_id(id);
+                event.set_timestamps(created_at, app.now());
+            }
+            _ => event.assign_id(app)?,
+        }
+        event.store(app)?;
+
+        if duplicates != DuplicatePolicy::Allow {
+            existing.insert(
+                import::duplicate_key(&event),
+                (event.id(), event.created_at()),
+            );
+        }
+        events.push(event);
+    }
+
+    Ok(events)
+}
+
+/// Parses an iCalendar file like [import_ics] without storing anything. Returns the events that would be imported
+/// (without ids yet) together with diagnostics for the `VEVENT`s that would be skipped and for questionable ones that
+/// would be imported anyway (e.g. with "floating" times). Events are checked against the config of `app` (see
+/// [Event::validate_for]) like when they're stored, so an import never fails halfway because of an invalid event
+pub fn validate_ics(app: &App, ics: &str) -> ImportPreview {
+    let mut preview = ImportPreview {
+        events: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+
+    for (index, vevent) in parse_vevents(ics).into_iter().enumerate() {
+        let vevent = match vevent {
+            Ok(vevent) => vevent,
+            Err(message) => {
+                preview.diagnostics.push(Diagnostic {
+                    index,
+                    message: format!("skipped: {}", message),
+                });
+                continue;
+            }
+        };
+
+        let mut event = match Event::unsaved(
+            &vevent.summary,
+            vevent.description.as_deref(),
+            vevent.start,
+            vevent.end,
+            app.now(),
+        ) {
+            Ok(event) => event,
+            Err(e) => {
+                preview.diagnostics.push(Diagnostic {
+                    index,
+                    message: format!("skipped: {}", e),
+                });
+                continue;
+            }
+        }
+        .with_timezone(vevent.tz)
+        .with_all_day(vevent.all_day);
+        event.location = vevent.location;
+        event.attendees = vevent.attendees;
+        event.color = vevent.color;
+        event.url = vevent.url;
+
+        if let Err(e) = event.validate_for(app) {
+            preview.diagnostics.push(Diagnostic {
+                index,
+                message: format!("skipped: {}", e),
+            });
+            continue;
+        }
+
+        preview.diagnostics.extend(
+            vevent
+                .warnings
+                .into_iter()
+                .map(|message| Diagnostic { index, message }),
+        );
+        preview.events.push(event);
+    }
+
+    preview
+}
+
+/// The properties of a `VEVENT` that are relevant for evnt
+struct VEvent {
+    summary: String,
+    description: Option<String>,
+    location: Option<String>,
+    attendees: Vec<String>,
+    color: Option<String>,
+    url: Option<String>,
+    start: DateTime<Utc>,
+    end: Option<DateTime<Utc>>,
+    /// The timezone from the `TZID` parameter of `DTSTART` (UTC if there is none)
+    tz: Tz,
+    /// Whether `DTSTART` is a `DATE` instead of a `DATE-TIME`
+    all_day: bool,
+    /// Things that are questionable about the `VEVENT`, but don't prevent importing it
+    warnings: Vec<String>,
+}
+
+/// A single content line of an iCalendar file
+struct Property {
+    /// The name of the property in uppercase (e.g. `DTSTART`)
+    name: String,
+    /// The parameters of the property as key value pairs (keys are uppercase)
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    /// Returns the value of the parameter with the given (uppercase) key
+    fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Parses all `VEVENT`s in an iCalendar file. Malformed `VEVENT`s result in an `Err` describing the problem
+fn parse_vevents(ics: &str) -> Vec<Result<VEvent, String>> {
+    let mut vevents = Vec::new();
+
+    // Properties of the VEVENT that is currently being parsed, `None` outside of VEVENTs
+    let mut properties: Option<Vec<Property>> = None;
+    // How deep we are inside components nested in a VEVENT (e.g. VALARM), their properties are ignored
+    let mut nested = 0;
+
+    for line in unfold(ics) {
+        let property = match parse_line(&line) {
+            Some(property) => property,
+            None => continue,
+        };
+        let is_vevent = property.value.eq_ignore_ascii_case("VEVENT");
+
+        match (property.name.as_str(), properties.as_mut()) {
+            ("BEGIN", None) if is_vevent => properties = Some(Vec::new()),
+            ("BEGIN", Some(_)) => nested += 1,
+            ("END", Some(_)) if nested > 0 => nested -= 1,
+            ("END", Some(_)) if is_vevent => {
+                vevents.push(parse_vevent(properties.take().unwrap_or_default()));
+            }
+            (_, Some(properties)) if nested == 0 => properties.push(property),
+            _ => {}
+        }
+    }
+
+    vevents
+}
+
+/// Converts the properties of a single `VEVENT` into a [VEvent]
+fn parse_vevent(properties: Vec<Property>) -> Result<VEvent, String> {
+    let mut summary = None;
+    let mut description = None;
+    let mut location = None;
+    let mut attendees = Vec::new();
+    let mut color = None;
+    let mut url = None;
+    let mut start = None;
+    let mut end = None;
+    let mut tz = Tz::UTC;
+    let mut all_day = false;
+    let mut warnings = Vec::new();
+
+    for property in properties {
+        match property.name.as_str() {
+            "SUMMARY" => summary = Some(unescape(&property.value)),
+            "DESCRIPTION" => description = Some(unescape(&property.value)),
+            "LOCATION" => location = Some(unescape(&property.value)),
+            "ATTENDEE" => attendees.extend(parse_attendee(&property)),
+            "COLOR" | "X-APPLE-CALENDAR-COLOR" => {
+                let value = property.value.trim();
+
+                if event::validate_color(value).is_ok() {
+                    color = Some(value.to_string());
+                } else {
+                    warnings.push(format!("unsupported color `{}` is ignored", value));
+                }
+            }
+            "URL" => {
+                let value = property.value.trim();
+
+                if event::validate_url(value).is_ok() {
+                    url = Some(value.to_string());
+                } else {
+                    warnings.push(format!("invalid url `{}` is ignored", value));
+                }
+            }
+            "DTSTART" => {
+                start = Some(parse_date_time(&property)?);
+
+                if let Some(tzid) = property.param("TZID") {
+                    tz = tzid.parse().unwrap_or(Tz::UTC);
+                }
+
+                all_day = is_date(&property);
+
+                if !all_day
+                    && property.param("TZID").is_none()
+                    && !property.value.trim().ends_with('Z')
+                {
+                    warnings.push("DTSTART has no timezone, it's interpreted as UTC".to_string());
+                }
+            }
+            "DTEND" => end = Some(parse_date_time(&property)?),
+            _ => {}
+        }
+    }
+
+    let summary = summary.ok_or_else(|| "VEVENT without SUMMARY".to_string())?;
+    let start = start.ok_or_else(|| "VEVENT without DTSTART".to_string())?;
+
+    // The DTEND of all-day events is the day after the last day, while evnt stores the last day itself. Single day
+    // events don't need an end at all
+    if all_day {
+        end = end
+            .map(|end| end - chrono::Duration::days(1))
+            .filter(|end| *end > start);
+    }
+
+    // Check everything that would make creating the event fail, so the VEVENT is skipped instead
+    event::validate_name(&summary).map_err(|e| e.to_string())?;
+
+    if end.is_some_and(|end| end < start) {
+        return Err("VEVENT ends before it starts".to_string());
+    }
+
+    Ok(VEvent {
+        summary,
+        description,
+        location,
+        attendees,
+        color,
+        url,
+        start,
+        end,
+        tz,
+        all_day,
+        warnings,
+    })
+}
+
+/// Joins folded lines (lines starting with a space or tab continue the previous line)
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in ics.lines() {
+        match (
+            line.strip_prefix(|c| c == ' ' || c == '\t'),
+            lines.last_mut(),
+        ) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    lines
+}
+
+/// Parses a content line into a [Property]. Returns `None` if the line isn't a valid content line
+fn parse_line(line: &str) -> Option<Property> {
+    // The value starts after the first colon that isn't inside a quoted parameter value
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ':' if !quoted => Some(i),
+        _ => None,
+    })?;
+
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+
+    // Parameters are separated by semicolons, except inside quoted values (e.g. `CN="Doe; Jane"`)
+    let mut quoted = false;
+    let mut parts = head.split(|c| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+
+        c == ';' && !quoted
+    });
+    let name = parts.next()?.trim().to_uppercase();
+
+    if name.is_empty() {
+        return None;
+    }
+
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| {
+            (
+                key.trim().to_uppercase(),
+                value.trim_matches('"').to_string(),
+            )
+        })
+        .collect();
+
+    Some(Property {
+        name,
+        params,
+        value: value.to_string(),
+    })
+}
+
+/// Formats an attendee (see [Event::attendees]) as an `ATTENDEE` property. Attendees that look like email addresses are
+/// exported as `mailto:` addresses, others as the `CN` (common name) parameter with a placeholder address. Double
+/// quotes can't be part of parameter values, so they are removed from names
+fn format_attendee(attendee: &str) -> String {
+    if attendee.contains('@') && !attendee.contains(char::is_whitespace) {
+        format!("ATTENDEE:mailto:{}", attendee)
+    } else {
+        format!(
+            "ATTENDEE;CN=\"{}\":invalid:nomail",
+            attendee.replace('"', "")
+        )
+    }
+}
+
+/// Reverses [format_attendee]: returns the `mailto:` address of the attendee, or their `CN` if there is no address
+fn parse_attendee(property: &Property) -> Option<String> {
+    let value = property.value.trim();
+    let address = value
+        .get(..7)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("mailto:"))
+        .map(|_| &value[7..]);
+
+    address
+        .or_else(|| property.param("CN"))
+        .map(str::trim)
+        .filter(|attendee| !attendee.is_empty())
+        .map(String::from)
+}
+
+/// Parses the value of an iCalendar `DATE-TIME` property (or `DATE`, which is interpreted as midnight) into UTC, taking
+/// the `TZID` parameter into account
+fn parse_date_time(property: &Property) -> Result<DateTime<Utc>, String> {
+    let value = property.value.trim();
+    let (value, utc) = match value.strip_suffix('Z') {
+        Some(value) => (value, true),
+        None => (value, false),
+    };
+
+    let naive = if is_date(property) {
+        NaiveDate::parse_from_str(value, "%Y%m%d").map(|date| date.and_hms(0, 0, 0))
+    } else {
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+    }
+    .map_err(|e| format!("invalid date-time `{}`: {}", value, e))?;
+
+    match property.param("TZID") {
+        Some(tzid) if !utc => {
+            let tz: Tz = tzid
+                .parse()
+                .map_err(|e| format!("unknown timezone `{}`: {}", tzid, e))?;
+
+            tz.from_local_datetime(&naive)
+                .earliest()
+                .map(|date_time| date_time.with_timezone(&Utc))
+                .ok_or_else(|| format!("`{}` doesn't exist in timezone `{}`", value, tzid))
+        }
+        _ => Ok(Utc.from_utc_datetime(&naive)),
+    }
+}
+
+/// Checks whether the value of a property is a `DATE` (instead of a `DATE-TIME`)
+fn is_date(property: &Property) -> bool {
+    property
+        .param("VALUE")
+        .is_some_and(|v| v.eq_ignore_ascii_case("DATE"))
+        || property.value.trim().len() == 8
+}
+
+/// Reverses [escape]
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n' | 'N')) => {
+                unescaped.push('\n');
+                chars.next();
+            }
+            ('\\', Some(escaped @ ('\\' | ';' | ','))) => {
+                unescaped.push(escaped);
+                chars.next();
+            }
+            (c, _) => unescaped.push(c),
+        }
+    }
+
+    unescaped
+}
+
+/// Formats a point in time as an iCalendar `DATE-TIME` in UTC (e.g. `20220404T103000Z`)
+fn format_date_time(date_time: DateTime<Utc>) -> String {
+    date_time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Formats a property (like `DTSTART`) with a time of the (not all-day) event. Recurring events are repeated at the
+/// same local time in their timezone, so their times get a `TZID` parameter (e.g.
+/// `DTSTART;TZID=Europe/Berlin:20220404T123000`) for the recurrences to follow daylight saving time like in evnt. Other
+/// times are in UTC
+fn time_property(name: &str, event: &Event, time: DateTime<Utc>) -> String {
+    if event.recurrence.is_some() {
+        let local = time.with_timezone(&event.tz).format("%Y%m%dT%H%M%S");
+        format!("{};TZID={}:{}", name, event.tz.name(), local)
+    } else {
+        format!("{}:{}", name, format_date_time(time))
+    }
+}
+
+/// Formats the (local) date of a point in time as an iCalendar `DATE` (e.g. `20220404`)
+fn format_date(date_time: DateTime<Tz>) -> String {
+    date_time.format("%Y%m%d").to_string()
+}
+
+/// Converts a recurrence into the value of an iCalendar `RRULE` property. `UNTIL` has to have the type of `DTSTART`, so
+/// for all-day events it's the date of the end of the recurrence in the timezone `tz` of the event
+fn format_rrule(recurrence: &Recurrence, all_day: bool, tz: Tz) -> String {
+    let mut rule = match recurrence.frequency {
+        Frequency::Daily => "FREQ=DAILY".to_string(),
+        Frequency::Weekly { weekday } => format!("FREQ=WEEKLY;BYDAY={}", format_weekday(weekday)),
+        // iCalendar skips months that don't have the given day, while evnt uses the last day of those months instead.
+        // Selecting the last existing day out of 28..=day gives the same result
+        Frequency::Monthly { day } if day > 28 => format!(
+            "FREQ=MONTHLY;BYMONTHDAY={};BYSETPOS=-1",
+            (28..=day.min(31))
+                .map(|day| day.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        Frequency::Monthly { day } => format!("FREQ=MONTHLY;BYMONTHDAY={}", day.max(1)),
+    };
+
+    if let Some(until) = recurrence.until {
+        let until = if all_day {
+            format_date(until.with_timezone(&tz))
+        } else {
+            format_date_time(until)
+        };
+        rule.push_str(&format!(";UNTIL={}", until));
+    }
+
+    rule
+}
+
+/// Returns the two letter iCalendar abbreviation of a weekday
+fn format_weekday(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+/// Escapes the characters that have a special meaning in iCalendar `TEXT` values
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Appends `line` to `ics`, folding it into multiple lines if it's longer than [MAX_LINE_LENGTH] octets. Lines end with
+/// CRLF and continuation lines start with a space, as required by the specification
+fn push_line(ics: &mut String, line: &str) {
+    let mut length = 0;
+
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            ics.push_str("\r\n ");
+            // The leading space counts towards the length of the continuation line
+            length = 1;
+        }
+
+        ics.push(c);
+        length += c.len_utf8();
+    }
+
+    ics.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::App;
+
+    #[test]
+    fn exports_events_as_ics() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Meeting, important",
+            Some("Line one\nLine two"),
+            Utc.ymd(2022, 4, 4).and_hms(10, 30, 0),
+            Some(Utc.ymd(2022, 4, 4).and_hms(11, 0, 0)),
+            &app,
+        )
+        .unwrap();
+
+        let ics = export_ics(std::slice::from_ref(&event), app.now()).unwrap();
+        let lines: Vec<&str> = ics.split_terminator("\r\n").collect();
+
+        assert_eq!(lines.first(), Some(&"BEGIN:VCALENDAR"));
+        assert_eq!(lines.last(), Some(&"END:VCALENDAR"));
+        assert!(lines.contains(&format!("UID:{}@evnt", event.id()).as_str()));
+        assert!(lines.contains(&"DTSTART:20220404T103000Z"));
+        assert!(lines.contains(&"DTEND:20220404T110000Z"));
+        assert!(lines.contains(&"SUMMARY:Meeting\\, important"));
+        assert!(lines.contains(&"DESCRIPTION:Line one\\nLine two"));
+        assert!(lines
+            .contains(&format!("LAST-MODIFIED:{}", format_date_time(event.updated_at())).as_str()));
+    }
+
+    #[test]
+    fn folds_long_lines() {
+        let mut ics = String::new();
+        let line = format!("DESCRIPTION:{}", "ä".repeat(100));
+        push_line(&mut ics, &line);
+
+        let lines: Vec<&str> = ics.split_terminator("\r\n").collect();
+
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|line| line.len() <= MAX_LINE_LENGTH));
+        assert!(lines[1..].iter().all(|line| line.starts_with(' ')));
+        assert_eq!(
+            lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| if i == 0 { *line } else { &line[1..] })
+                .collect::<String>(),
+            line
+        );
+    }
+
+    #[test]
+    fn imports_events_from_ics() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let ics = "BEGIN:VCALENDAR\r\n\
+                   VERSION:2.0\r\n\
+                   BEGIN:VEVENT\r\n\
+                   SUMMARY:Standup\\, daily\r\n\
+                   DESCRIPTION:A very long description that got folded over\r\n  multiple lines\r\n\
+                   DTSTART:20220404T093000Z\r\n\
+                   BEGIN:VALARM\r\n\
+                   DESCRIPTION:Reminder\r\n\
+                   END:VALARM\r\n\
+                   END:VEVENT\r\n\
+                   BEGIN:VEVENT\r\n\
+                   SUMMARY:Lunch\r\n\
+                   DTSTART;TZID=Europe/Berlin:20220704T120000\r\n\
+                   DTEND;TZID=\"Europe/Berlin\":20220704T130000\r\n\
+                   END:VEVENT\r\n\
+                   BEGIN:VEVENT\r\n\
+                   SUMMARY:Missing start\r\n\
+                   END:VEVENT\r\n\
+                   BEGIN:VEVENT\r\n\
+                   SUMMARY: \r\n\
+                   DTSTART:20220404T093000Z\r\n\
+                   END:VEVENT\r\n\
+                   END:VCALENDAR\r\n";
+
+        let events = import_ics(&app, ics, DuplicatePolicy::Allow).unwrap();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].name, "Standup, daily");
+        assert_eq!(
+            events[0].description.as_deref(),
+            Some("A very long description that got folded over multiple lines")
+        );
+        assert_eq!(events[0].date_time, Utc.ymd(2022, 4, 4).and_hms(9, 30, 0));
+        assert_eq!(events[1].date_time, Utc.ymd(2022, 7, 4).and_hms(10, 0, 0));
+        assert_eq!(events[1].tz, chrono_tz::Europe::Berlin);
+        assert_eq!(
+            events[1].end_time,
+            Some(Utc.ymd(2022, 7, 4).and_hms(11, 0, 0))
+        );
+
+        assert_eq!(crate::event::read_events(&app).unwrap(), events);
+    }
+
+    #[test]
+    fn validates_ics_without_storing() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let ics = "BEGIN:VCALENDAR\r\n\
+                   BEGIN:VEVENT\r\n\
+                   SUMMARY:Standup\r\n\
+                   DTSTART:20220404T093000Z\r\n\
+                   END:VEVENT\r\n\
+                   BEGIN:VEVENT\r\n\
+                   SUMMARY:Missing start\r\n\
+                   END:VEVENT\r\n\
+                   BEGIN:VEVENT\r\n\
+                   SUMMARY:Floating\r\n\
+                   DTSTART:20220404T120000\r\n\
+                   END:VEVENT\r\n\
+                   END:VCALENDAR\r\n";
+
+        let preview = validate_ics(&app, ics);
+
+        let names: Vec<&str> = preview
+            .events
+            .iter()
+            .map(|event| event.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Standup", "Floating"]);
+        let indices: Vec<usize> = preview
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.index)
+            .collect();
+        assert_eq!(indices, vec![1, 2]);
+        assert!(preview.diagnostics[0].message.contains("DTSTART"));
+
+        assert!(crate::event::read_events(&app).unwrap().is_empty());
+        assert!(!app.events_dir.join(".next_id").exists());
+    }
+
+    #[test]
+    fn skips_events_outside_the_year_bounds() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let mut app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+        app.config.year_bounds = Some((2000, 2030));
+
+        let ics = "BEGIN:VCALENDAR\r\n\
+                   BEGIN:VEVENT\r\n\
+                   SUMMARY:Standup\r\n\
+                   DTSTART:20220404T093000Z\r\n\
+                   END:VEVENT\r\n\
+                   BEGIN:VEVENT\r\n\
+                   SUMMARY:Typo\r\n\
+                   DTSTART:20920404T093000Z\r\n\
+                   END:VEVENT\r\n\
+                   END:VCALENDAR\r\n";
+
+        let preview = validate_ics(&app, ics);
+        assert_eq!(preview.events.len(), 1);
+        assert_eq!(preview.diagnostics[0].index, 1);
+        assert!(preview.diagnostics[0].message.starts_with("skipped: "));
+
+        let imported = import_ics(&app, ics, DuplicatePolicy::Allow).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(crate::event::read_events(&app).unwrap(), imported);
+    }
+
+    #[test]
+    fn handles_duplicates_on_import() {
+        use crate::clock::FixedClock;
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let created = Utc.ymd(2022, 4, 1).and_hms(8, 0, 0);
+        let app = App::new(temp_data_dir.path()).with_clock(FixedClock(created));
+        crate::utils::create_dirs(&app).unwrap();
+
+        let ics = |location| {
+            format!(
+                "BEGIN:VCALENDAR\r\n\
+                 BEGIN:VEVENT\r\n\
+                 SUMMARY:Standup\r\n\
+                 LOCATION:{}\r\n\
+                 DTSTART:20220404T093000Z\r\n\
+                 END:VEVENT\r\n\
+                 END:VCALENDAR\r\n",
+                location
+            )
+        };
+
+        let first = import_ics(&app, &ics("Room 1"), DuplicatePolicy::Skip).unwrap();
+        assert_eq!(first.len(), 1);
+        assert!(import_ics(&app, &ics("Room 1"), DuplicatePolicy::Skip)
+            .unwrap()
+            .is_empty());
+        assert_eq!(crate::event::read_events(&app).unwrap(), first);
+
+        let updated = Utc.ymd(2022, 4, 2).and_hms(8, 0, 0);
+        let app = app.with_clock(FixedClock(updated));
+        let overwritten = import_ics(&app, &ics("Room 2"), DuplicatePolicy::Overwrite).unwrap();
+        assert_eq!(overwritten[0].id(), first[0].id());
+        assert_eq!(overwritten[0].created_at(), created);
+        assert_eq!(overwritten[0].updated_at(), updated);
+        assert_eq!(crate::event::read_events(&app).unwrap(), overwritten);
+
+        import_ics(&app, &ics("Room 2"), DuplicatePolicy::Allow).unwrap();
+        assert_eq!(crate::event::read_events(&app).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn streams_events_to_writers() {
+        /// Records every write separately
+        struct Chunks(Vec<Vec<u8>>);
+
+        impl Write for Chunks {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.push(buf.to_vec());
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        for name in ["First", "Second"] {
+            Event::new(
+                name,
+                None,
+                Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+                None,
+                &app,
+            )
+            .unwrap()
+            .store(&app)
+            .unwrap();
+        }
+
+        let mut chunks = Chunks(Vec::new());
+        export_ics_to(
+            event::iter_events(&app).unwrap().map(Result::unwrap),
+            app.now(),
+            &mut chunks,
+        )
+        .unwrap();
+
+        // The header, one chunk per event and the footer
+        assert_eq!(chunks.0.len(), 4);
+        let chunk = |i: usize| String::from_utf8(chunks.0[i].clone()).unwrap();
+        assert!(chunk(0).starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(chunk(1).starts_with("BEGIN:VEVENT\r\n"));
+        assert!(chunk(2).ends_with("END:VEVENT\r\n"));
+        assert_eq!(chunk(3), "END:VCALENDAR\r\n");
+
+        let without_dtstamp = |ics: &str| -> String {
+            ics.split_inclusive("\r\n")
+                .filter(|line| !line.starts_with("DTSTAMP"))
+                .collect()
+        };
+        let events: Vec<Event> = event::iter_events(&app)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            without_dtstamp(&String::from_utf8(chunks.0.concat()).unwrap()),
+            without_dtstamp(&export_ics(&events, app.now()).unwrap())
+        );
+    }
+
+    #[test]
+    fn round_trips_colors() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = |color| {
+            Event::new(
+                "Meeting",
+                None,
+                Utc.ymd(2022, 4, 4).and_hms(10, 30, 0),
+                None,
+                &app,
+            )
+            .unwrap()
+            .with_color(color)
+            .unwrap()
+        };
+        let events = [event("#1E90FF"), event("red")];
+
+        let ics = export_ics(&events, app.now()).unwrap();
+        assert!(ics.contains("X-APPLE-CALENDAR-COLOR:#1E90FF\r\n"));
+        assert!(ics.contains("COLOR:red\r\n"));
+
+        let imported = import_ics(&app, &ics, DuplicatePolicy::Allow).unwrap();
+        assert_eq!(imported[0].color.as_deref(), Some("#1E90FF"));
+        assert_eq!(imported[1].color.as_deref(), Some("red"));
+    }
+
+    #[test]
+    fn round_trips_urls() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Call",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 30, 0),
+            None,
+            &app,
+        )
+        .unwrap()
+        .with_url("https://example.com/join?id=1,2;3")
+        .unwrap();
+
+        let ics = export_ics(&[event], app.now()).unwrap();
+        assert!(ics.contains("URL:https://example.com/join?id=1,2;3\r\n"));
+
+        let imported = import_ics(&app, &ics, DuplicatePolicy::Allow).unwrap();
+        assert_eq!(
+            imported[0].url.as_deref(),
+            Some("https://example.com/join?id=1,2;3")
+        );
+
+        let invalid = ics.replace("URL:https://example.com/join?id=1,2;3", "URL:not a url");
+        let preview = validate_ics(&app, &invalid);
+        assert_eq!(preview.events[0].url, None);
+        assert_eq!(
+            preview.diagnostics[0].message,
+            "invalid url `not a url` is ignored"
+        );
+    }
+
+    #[test]
+    fn round_trips_attendees() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Meeting",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 30, 0),
+            None,
+            &app,
+        )
+        .unwrap()
+        .with_attendees(&[
+            "zoe@example.com",
+            "Doe; Jane",
+            "Ämil Müller",
+            "adam@example.com",
+        ]);
+
+        let ics = export_ics(std::slice::from_ref(&event), app.now()).unwrap();
+        assert!(ics.contains("ATTENDEE:mailto:zoe@example.com\r\n"));
+        assert!(ics.contains("ATTENDEE;CN=\"Doe; Jane\":invalid:nomail\r\n"));
+
+        let imported = import_ics(&app, &ics, DuplicatePolicy::Allow).unwrap();
+        assert_eq!(imported[0].attendees, event.attendees);
+    }
+
+    #[test]
+    fn round_trips_locations() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Meeting",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 30, 0),
+            None,
+            &app,
+        )
+        .unwrap()
+        .with_location("Café Zürich; Room 3, 2nd floor");
+
+        let ics = export_ics(std::slice::from_ref(&event), app.now()).unwrap();
+        assert!(ics.contains("LOCATION:Café Zürich\\; Room 3\\, 2nd floor\r\n"));
+
+        let imported = import_ics(&app, &ics, DuplicatePolicy::Allow).unwrap();
+        assert_eq!(imported[0].location, event.location);
+    }
+
+    #[test]
+    fn round_trips_all_day_events() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let conference = Event::new(
+            "Conference",
+            None,
+            Utc.ymd(2022, 6, 1).and_hms(0, 0, 0),
+            Some(Utc.ymd(2022, 6, 3).and_hms(0, 0, 0)),
+            &app,
+        )
+        .unwrap()
+        .with_all_day(true);
+
+        let ics = export_ics(std::slice::from_ref(&conference), app.now()).unwrap();
+        assert!(ics.contains("DTSTART;VALUE=DATE:20220601\r\n"));
+        assert!(ics.contains("DTEND;VALUE=DATE:20220604\r\n"));
+
+        let imported = import_ics(&app, &ics, DuplicatePolicy::Allow).unwrap();
+        assert!(imported[0].all_day);
+        assert_eq!(imported[0].start(), conference.start());
+        assert_eq!(imported[0].end(), conference.end());
+    }
+
+    #[test]
+    fn exports_recurrences_in_the_event_timezone() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let until = Utc.ymd(2022, 5, 2).and_hms(10, 30, 0);
+        let mut standup = Event::new(
+            "Standup",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(7, 30, 0),
+            Some(Utc.ymd(2022, 4, 4).and_hms(7, 45, 0)),
+            &app,
+        )
+        .unwrap()
+        .with_timezone(chrono_tz::Europe::Berlin);
+        standup.recurrence = Some(Recurrence::new(Frequency::Daily, Some(until)));
+
+        let ics = export_ics(std::slice::from_ref(&standup), app.now()).unwrap();
+        assert!(ics.contains("DTSTART;TZID=Europe/Berlin:20220404T093000\r\n"));
+        assert!(ics.contains("DTEND;TZID=Europe/Berlin:20220404T094500\r\n"));
+        assert!(ics.contains("RRULE:FREQ=DAILY;UNTIL=20220502T103000Z\r\n"));
+
+        let mut holiday = standup.with_all_day(true);
+        holiday.recurrence = Some(Recurrence::new(Frequency::Daily, Some(until)));
+
+        let ics = export_ics(std::slice::from_ref(&holiday), app.now()).unwrap();
+        assert!(ics.contains("DTSTART;VALUE=DATE:20220404\r\n"));
+        assert!(ics.contains("RRULE:FREQ=DAILY;UNTIL=20220502\r\n"));
+    }
+}
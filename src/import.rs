@@ -0,0 +1,50 @@
+//! Types shared by the functions importing events from other formats (see [crate::ics], [crate::csv] and
+//! [crate::backup])
+
+use chrono::{DateTime, Utc};
+
+use crate::event::Event;
+
+/// What importing a file would do, without actually storing anything (see [crate::ics::validate_ics],
+/// [crate::csv::validate_csv] and [crate::backup::validate_import])
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportPreview {
+    /// The events that would be created. Ids are only final for events that keep their id (see
+    /// [crate::backup::import_all]), otherwise new ids are generated when importing
+    pub events: Vec<Event>,
+    /// Problems with individual entries of the file, like entries that would be skipped
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// What to do with imported events that are duplicates of already stored events (or of events imported before them).
+/// Events are duplicates if they have the same name, time and description
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Import duplicates as separate events anyway
+    Allow,
+    /// Don't import duplicates, so importing the same file multiple times doesn't change anything
+    Skip,
+    /// Replace the existing event with the imported one, keeping the id of the existing event
+    Overwrite,
+}
+
+/// The fields that identify duplicate events (see [DuplicatePolicy])
+pub(crate) type DuplicateKey = (String, DateTime<Utc>, Option<String>);
+
+/// Returns the key that identifies duplicates of the event (see [DuplicatePolicy])
+pub(crate) fn duplicate_key(event: &Event) -> DuplicateKey {
+    (
+        event.name.clone(),
+        event.date_time,
+        event.description.clone(),
+    )
+}
+
+/// A problem with a single entry of an imported file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The position of the entry in the file (starting at 0, counting all entries including skipped ones)
+    pub index: usize,
+    /// What is wrong with the entry and what importing it would do
+    pub message: String,
+}
@@ -1,31 +1,292 @@
+pub mod backup;
+pub mod calendar;
+pub mod cli;
+pub mod clock;
+pub mod codec;
+pub mod config;
+pub mod conflict;
+pub mod csv;
+pub mod error;
 pub mod event;
+pub mod fsck;
+pub mod ics;
+pub mod import;
+pub mod output;
+pub mod parse;
+pub mod query;
+pub mod recurrence;
+pub mod reminder;
+pub mod schema;
+pub mod stats;
+pub mod template;
 pub mod utils;
+pub mod watch;
 
-use std::path::{Path, PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use anyhow::Result;
+use chrono_tz::Tz;
+
+use crate::{
+    clock::{Clock, SystemClock},
+    codec::{Codec, JsonCodec},
+    config::Config,
+    error::{CalendarError, Context},
+};
 
 /// Stores the configuration of the program
 pub struct App {
     /// The directory in which the programs data is stored
     pub data_dir: PathBuf,
 
-    /// The directory in which the events are stored, should be the "events" subdirectory of [App::data_dir]
+    /// The directory in which the events are stored, should be the "events" subdirectory of [App::data_dir] (or a
+    /// subdirectory of that for named calendars, see [App::calendar])
     pub events_dir: PathBuf,
+
+    /// The name of the calendar the events are read from and written to, `None` for the default calendar
+    pub calendar: Option<String>,
+
+    /// The preferences of the user, loaded from the config file by [run] (the defaults until then)
+    pub config: Config,
+
+    /// Whether nothing may be written to [App::data_dir] (see [App::read_only])
+    pub read_only: bool,
+
+    /// Where the current time comes from (the [SystemClock] unless set with [App::with_clock]), used e.g. for the
+    /// timestamps of events
+    pub clock: Arc<dyn Clock + Send + Sync>,
+
+    /// How events are encoded when they're written (the [JsonCodec] unless set with [App::with_codec]). Files are read
+    /// with whatever codec they were written with
+    pub codec: Arc<dyn Codec + Send + Sync>,
+
+    /// The timezone times are shown in, overriding the one from the config (see [App::display_timezone])
+    pub display_tz: Option<Tz>,
 }
 
 impl App {
+    /// Creates an [App] that stores its data in `data_dir`. The directories aren't created (or checked) until [run] is
+    /// called, use [App::try_new] to find out about unusable paths right away
     pub fn new<P: AsRef<Path> + ToOwned>(data_dir: P) -> Self {
         Self {
             data_dir: data_dir.as_ref().to_path_buf(),
             events_dir: data_dir.as_ref().to_path_buf().join("events/"),
+            calendar: None,
+            config: Config::default(),
+            read_only: false,
+            clock: Arc::new(SystemClock),
+            codec: Arc::new(JsonCodec),
+            display_tz: None,
+        }
+    }
+
+    /// Makes the [App] get the current time from `clock` instead of the system clock, e.g. a
+    /// [clock::FixedClock] in tests
+    pub fn with_clock(mut self, clock: impl Clock + Send + Sync + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Makes the [App] write events with `codec` instead of as json, e.g. a [codec::BincodeCodec] for speed
+    pub fn with_codec(mut self, codec: impl Codec + Send + Sync + 'static) -> Self {
+        self.codec = Arc::new(codec);
+        self
+    }
+
+    /// Returns the current time according to [App::clock]
+    pub fn now(&self) -> chrono::DateTime<chrono::Utc> {
+        self.clock.now()
+    }
+
+    /// Makes the [App] show times in `tz`, regardless of the config (see [App::display_timezone])
+    pub fn with_display_timezone(mut self, tz: Tz) -> Self {
+        self.display_tz = Some(tz);
+        self
+    }
+
+    /// Returns the timezone in which times are shown to the user: [App::display_tz] if set, otherwise
+    /// [Config::display_timezone], otherwise the timezone of the system (see [utils::system_timezone]), falling back to
+    /// UTC. Events are still stored in UTC, this only changes how their times are presented
+    pub fn display_timezone(&self) -> Tz {
+        self.display_tz
+            .or(self.config.display_timezone)
+            .or_else(utils::system_timezone)
+            .unwrap_or(Tz::UTC)
+    }
+
+    /// Makes the [App] read-only, e.g. to inspect a calendar that is mounted read-only or is a snapshot. Events can
+    /// still be read, but everything that would write to [App::data_dir] (like [event::Event::store] and
+    /// [event::delete_event]) fails with [CalendarError::ReadOnly]. [run] doesn't create the directories and the config
+    /// file, and old event files aren't converted to the [App::codec] when they are read
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Like [App::new], but fails with [CalendarError::InvalidDataDir] if `data_dir` can't be used: if it (or the
+    /// closest of its ancestors that exists, in which it would be created) isn't a directory or is read-only
+    pub fn try_new<P: AsRef<Path> + ToOwned>(data_dir: P) -> Result<Self, CalendarError> {
+        let app = Self::new(data_dir);
+
+        let existing = app
+            .data_dir
+            .ancestors()
+            .find(|path| path.exists())
+            .unwrap_or_else(|| Path::new("."));
+        let metadata = fs::metadata(existing).with_context(|| {
+            format!(
+                "failed to read metadata of `{}`",
+                existing.to_string_lossy()
+            )
+        })?;
+
+        if !metadata.is_dir() {
+            return Err(CalendarError::InvalidDataDir(format!(
+                "`{}` is not a directory",
+                existing.to_string_lossy()
+            )));
+        }
+
+        if metadata.permissions().readonly() {
+            return Err(CalendarError::InvalidDataDir(format!(
+                "`{}` is not writable",
+                existing.to_string_lossy()
+            )));
+        }
+
+        Ok(app)
+    }
+
+    /// Returns an [App] for the named calendar `name`, whose events are stored in the `name` subdirectory of the
+    /// default calendar's "events" directory. Each calendar is a separate directory, so event ids are only unique
+    /// within a calendar (the same id can exist in different calendars).
+    ///
+    /// Calendar names may only contain ASCII letters, digits, `-` and `_`, and can't look like a month (e.g.
+    /// `2022-04`), since those names are used for month directories (see [event::Layout::Monthly])
+    pub fn calendar(&self, name: &str) -> Result<Self, CalendarError> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            || event::parse_month(name).is_some()
+        {
+            return Err(CalendarError::InvalidCalendarName(name.to_string()));
         }
+
+        Ok(Self {
+            data_dir: self.data_dir.clone(),
+            events_dir: self.data_dir.join("events/").join(name),
+            calendar: Some(name.to_string()),
+            config: self.config.clone(),
+            read_only: self.read_only,
+            clock: self.clock.clone(),
+            codec: self.codec.clone(),
+            display_tz: self.display_tz,
+        })
     }
 }
 
 /// Run the program
-pub fn run(app: App) -> Result<()> {
-    utils::create_dirs(&app)?;
+pub fn run(mut app: App, command: cli::Command) -> Result<()> {
+    if !app.read_only {
+        utils::create_dirs(&app)?;
+    }
+    app.config = Config::load(&app)?;
+
+    cli::run_command(&app, command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_calendars_use_subdirectories() {
+        let app = App::new("/data");
+
+        let work = app.calendar("work").unwrap();
+        assert_eq!(work.events_dir, app.events_dir.join("work"));
+        assert_eq!(work.calendar.as_deref(), Some("work"));
+
+        for name in ["", "..", "a/b", ".hidden", "2022-04"] {
+            assert!(matches!(
+                app.calendar(name),
+                Err(CalendarError::InvalidCalendarName(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn resolves_display_timezone() {
+        let mut app = App::new("/data");
+        app.config.display_timezone = Some(Tz::Asia__Tokyo);
+        assert_eq!(app.display_timezone(), Tz::Asia__Tokyo);
 
-    Ok(())
+        let app = app.with_display_timezone(Tz::Europe__Berlin);
+        assert_eq!(app.display_timezone(), Tz::Europe__Berlin);
+        assert_eq!(
+            app.calendar("work").unwrap().display_timezone(),
+            Tz::Europe__Berlin
+        );
+    }
+
+    #[test]
+    fn read_only_apps_only_read() {
+        use chrono::{TimeZone, Utc};
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        utils::create_dirs(&app).unwrap();
+
+        let event = event::Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        event.store(&app).unwrap();
+
+        let app = app.read_only();
+        assert_eq!(event::read_events(&app).unwrap(), vec![event.clone()]);
+        assert!(matches!(event.store(&app), Err(CalendarError::ReadOnly)));
+        assert!(matches!(
+            event::delete_event(&app, event.id()),
+            Err(CalendarError::ReadOnly)
+        ));
+        assert!(app.calendar("work").unwrap().read_only);
+        assert_eq!(Config::load(&app).unwrap(), Config::default());
+        assert!(!app.data_dir.join(config::CONFIG_FILE).exists());
+    }
+
+    #[test]
+    fn try_new_rejects_unusable_data_dirs() {
+        let temp_dir = assert_fs::TempDir::new().unwrap();
+        assert!(App::try_new(temp_dir.path().join("evnt")).is_ok());
+
+        let file = temp_dir.path().join("file");
+        fs::write(&file, "").unwrap();
+        let error = App::try_new(file.join("evnt")).err().unwrap();
+        assert!(matches!(error, CalendarError::InvalidDataDir(_)));
+        assert!(error.to_string().contains("is not a directory"));
+
+        // The permissions are checked instead of trying to write, so this also fails when running as root
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let read_only = temp_dir.path().join("read-only");
+            fs::create_dir(&read_only).unwrap();
+            fs::set_permissions(&read_only, fs::Permissions::from_mode(0o555)).unwrap();
+
+            let error = App::try_new(read_only.join("evnt")).err().unwrap();
+            assert!(matches!(error, CalendarError::InvalidDataDir(_)));
+            assert!(error.to_string().contains("read-only` is not writable"));
+        }
+    }
 }
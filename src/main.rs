@@ -1,15 +1,20 @@
-use std::{env, error::Error};
+use std::error::Error;
 
-use evnt::App;
+use clap::Parser;
+use evnt::{cli::Cli, App};
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let app = App::new(&format!(
-        "{}{}",
-        env::var("HOME").unwrap(),
-        "/.local/share/evnt"
-    ));
+    let cli = Cli::parse();
+    let mut app = if cli.read_only {
+        App::new(cli.data_dir()?).read_only()
+    } else {
+        App::try_new(cli.data_dir()?)?
+    };
+    if let Some(tz) = cli.tz {
+        app = app.with_display_timezone(tz);
+    }
 
-    evnt::run(app)?;
+    evnt::run(app, cli.command)?;
 
     Ok(())
 }
@@ -0,0 +1,388 @@
+//! Formatting lists of events for output, either for humans or in machine-readable formats
+
+use std::io::Write;
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use clap::ArgEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Context, Result},
+    event::Event,
+    ics, App,
+};
+
+/// The formats events can be printed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// One line per event with the id followed by the event's [std::fmt::Display] form (local start time, name and
+    /// description), meant for humans
+    Plain,
+    /// A json array of the events (the same fields as the stored files)
+    Json,
+    /// Comma separated values with a header row of `name,description,date_time,id,location`. Times are in UTC (RFC
+    /// 3339)
+    Csv,
+    /// An iCalendar file (see [ics::export_ics])
+    Ics,
+    /// One json object per line (the same fields as the stored files), without an enclosing array (see
+    /// [export_ndjson])
+    Ndjson,
+}
+
+/// Formats the events in the given format with its [Format::writer] for `app`. Every line (including the last one) ends
+/// with a line break
+pub fn format_events(app: &App, events: &[Event], format: Format) -> Result<String> {
+    let mut output = Vec::new();
+    format.writer(app).write(events, &mut output)?;
+
+    Ok(String::from_utf8(output).expect("event writers only write valid UTF-8"))
+}
+
+/// Renders a list of events into some output format. The built-in writers are the ones for the variants of [Format]
+/// (see [Format::writer]), frontends can implement their own
+pub trait EventWriter {
+    /// Writes all the events to `out`
+    fn write(&self, events: &[Event], out: &mut dyn Write) -> Result<()>;
+}
+
+impl Format {
+    /// Returns the built-in writer for this format. [Format::Plain] shows the times in the [App::display_timezone] of
+    /// `app` (the machine-readable formats always use the stored times), [Format::Ics] is stamped with [App::now]
+    pub fn writer(self, app: &App) -> Box<dyn EventWriter> {
+        match self {
+            Format::Plain => Box::new(PlainWriter {
+                tz: Some(app.display_timezone()),
+            }),
+            Format::Json => Box::new(JsonWriter),
+            Format::Csv => Box::new(CsvWriter),
+            Format::Ics => Box::new(IcsWriter { now: app.now() }),
+            Format::Ndjson => Box::new(NdjsonWriter),
+        }
+    }
+}
+
+/// Writes events in [Format::Plain]
+#[derive(Debug, Default)]
+pub struct PlainWriter {
+    /// The timezone the start times are shown in, `None` for the timezone of each event
+    pub tz: Option<Tz>,
+}
+
+impl EventWriter for PlainWriter {
+    fn write(&self, events: &[Event], out: &mut dyn Write) -> Result<()> {
+        for event in events {
+            let display = event.display_in(self.tz.unwrap_or(event.tz));
+
+            writeln!(out, "{:>6}  {}", event.id(), display)
+                .with_context(|| "failed to write events")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes events in [Format::Json]
+pub struct JsonWriter;
+
+impl EventWriter for JsonWriter {
+    fn write(&self, events: &[Event], out: &mut dyn Write) -> Result<()> {
+        let json =
+            serde_json::to_string_pretty(events).with_context(|| "failed to serialize events")?;
+
+        writeln!(out, "{}", json).with_context(|| "failed to write events")
+    }
+}
+
+/// Writes events in [Format::Csv]
+pub struct CsvWriter;
+
+impl EventWriter for CsvWriter {
+    fn write(&self, events: &[Event], out: &mut dyn Write) -> Result<()> {
+        let mut writer = ::csv::Writer::from_writer(out);
+        writer
+            .write_record(["name", "description", "date_time", "id", "location"])
+            .with_context(|| "failed to write events")?;
+
+        for event in events {
+            writer
+                .write_record([
+                    event.name.as_str(),
+                    event.description.as_deref().unwrap_or_default(),
+                    &event.date_time.to_rfc3339(),
+                    &event.id().to_string(),
+                    event.location.as_deref().unwrap_or_default(),
+                ])
+                .with_context(|| "failed to write events")?;
+        }
+
+        writer.flush().with_context(|| "failed to write events")
+    }
+}
+
+/// Writes events in [Format::Ndjson] (see [export_ndjson])
+pub struct NdjsonWriter;
+
+impl EventWriter for NdjsonWriter {
+    fn write(&self, events: &[Event], mut out: &mut dyn Write) -> Result<()> {
+        export_ndjson(events.iter().cloned(), &mut out)
+    }
+}
+
+/// Writes the events to `out` as json lines (NDJSON): every event is serialized into a complete json object on a line
+/// of its own, without an enclosing array. Each event is written as soon as the iterator yields it, so together with
+/// [crate::event::iter_events] any number of events can be exported in constant memory and consumers can process them
+/// line by line
+pub fn export_ndjson<W: Write>(events: impl Iterator<Item = Event>, out: &mut W) -> Result<()> {
+    for event in events {
+        serde_json::to_writer(&mut *out, &event).with_context(|| {
+            format!(
+                "failed to serialize event `{}` (id: {})",
+                event.name,
+                event.id()
+            )
+        })?;
+        writeln!(out).with_context(|| "failed to write events")?;
+    }
+
+    Ok(())
+}
+
+/// Writes events in [Format::Ics] (see [ics::export_ics_to])
+pub struct IcsWriter {
+    /// The time of the export, written as the `DTSTAMP` of every event
+    pub now: DateTime<Utc>,
+}
+
+impl EventWriter for IcsWriter {
+    fn write(&self, events: &[Event], mut out: &mut dyn Write) -> Result<()> {
+        ics::export_ics_to(events.iter().cloned(), self.now, &mut out)
+    }
+}
+
+/// Describes when the event starts relative to `now` in words, like `in 2 hours`, `tomorrow` or `3 days ago`. The
+/// amount is rounded down to the largest fitting unit (minutes, hours, days, weeks, months of 30 days or years of 365
+/// days), a difference of less than a minute is `just now`. Days are counted between the local dates in the timezone of
+/// the event (see [humanize_relative_in])
+pub fn humanize_relative(event: &Event, now: DateTime<Utc>) -> String {
+    humanize_relative_in(event, now, event.tz)
+}
+
+/// Like [humanize_relative], but counts days between the local dates in the timezone `tz`, so an event on the next day
+/// is `tomorrow` even if it's more than 24 hours away
+pub fn humanize_relative_in(event: &Event, now: DateTime<Utc>, tz: Tz) -> String {
+    const UNITS: [(&str, i64); 6] = [
+        ("year", 365 * 24 * 60 * 60),
+        ("month", 30 * 24 * 60 * 60),
+        ("week", 7 * 24 * 60 * 60),
+        ("day", 24 * 60 * 60),
+        ("hour", 60 * 60),
+        ("minute", 60),
+    ];
+
+    let seconds = (event.date_time - now).num_seconds();
+    let future = seconds >= 0;
+    let seconds = seconds.abs();
+
+    let (unit, count) = match UNITS.iter().find(|(_, length)| seconds >= *length) {
+        Some((unit, length)) => (*unit, seconds / length),
+        None => return "just now".to_string(),
+    };
+
+    let count = if unit == "day" {
+        let date = |time: DateTime<Utc>| time.with_timezone(&tz).date().naive_local();
+        (date(event.date_time) - date(now)).num_days().abs().max(1)
+    } else {
+        count
+    };
+
+    match (unit, count, future) {
+        ("day", 1, true) => "tomorrow".to_string(),
+        ("day", 1, false) => "yesterday".to_string(),
+        _ => {
+            let plural = if count == 1 { "" } else { "s" };
+
+            if future {
+                format!("in {} {}{}", count, unit, plural)
+            } else {
+                format!("{} {}{} ago", count, unit, plural)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::App;
+
+    #[test]
+    fn writes_events_with_custom_writers() {
+        struct NameWriter;
+
+        impl EventWriter for NameWriter {
+            fn write(&self, events: &[Event], out: &mut dyn Write) -> Result<()> {
+                for event in events {
+                    writeln!(out, "{}", event.name).with_context(|| "failed to write events")?;
+                }
+
+                Ok(())
+            }
+        }
+
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let events = [Event::new(
+            "Meeting",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap()];
+
+        let mut output = Vec::new();
+        NameWriter.write(&events, &mut output).unwrap();
+        assert_eq!(output, b"Meeting\n");
+
+        let ics = format_events(&app, &events, Format::Ics).unwrap();
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.contains("SUMMARY:Meeting\r\n"));
+    }
+
+    #[test]
+    fn formats_events_as_csv() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let events = [
+            Event::new(
+                "Meeting, important",
+                Some("Bring \"notes\"\nand coffee"),
+                Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+                None,
+                &app,
+            )
+            .unwrap()
+            .with_location("Room 1, Zürich"),
+            Event::new(
+                "Lunch",
+                None,
+                Utc.ymd(2022, 4, 4).and_hms(12, 0, 0),
+                None,
+                &app,
+            )
+            .unwrap(),
+        ];
+
+        assert_eq!(
+            format_events(&app, &events, Format::Csv).unwrap(),
+            format!(
+                "name,description,date_time,id,location\n\
+                 \"Meeting, important\",\"Bring \"\"notes\"\"\nand coffee\",2022-04-04T10:00:00+00:00,{},\
+                 \"Room 1, Zürich\"\n\
+                 Lunch,,2022-04-04T12:00:00+00:00,{},\n",
+                events[0].id(),
+                events[1].id()
+            )
+        );
+
+        let json = format_events(&app, &events, Format::Json).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<Event>>(&json).unwrap(), events);
+    }
+
+    #[test]
+    fn humanizes_relative_times() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        let relative = |now| humanize_relative(&event, now);
+
+        assert_eq!(relative(Utc.ymd(2022, 4, 4).and_hms(10, 0, 0)), "just now");
+        assert_eq!(relative(Utc.ymd(2022, 4, 4).and_hms(10, 0, 59)), "just now");
+        assert_eq!(
+            relative(Utc.ymd(2022, 4, 4).and_hms(9, 59, 0)),
+            "in 1 minute"
+        );
+        assert_eq!(
+            relative(Utc.ymd(2022, 4, 4).and_hms(10, 1, 0)),
+            "1 minute ago"
+        );
+        assert_eq!(
+            relative(Utc.ymd(2022, 4, 4).and_hms(7, 30, 0)),
+            "in 2 hours"
+        );
+        assert_eq!(relative(Utc.ymd(2022, 4, 3).and_hms(9, 0, 0)), "tomorrow");
+        assert_eq!(relative(Utc.ymd(2022, 4, 5).and_hms(10, 0, 0)), "yesterday");
+        assert_eq!(
+            relative(Utc.ymd(2022, 4, 2).and_hms(22, 30, 0)),
+            "in 2 days"
+        );
+        // It's already April 3rd in Berlin
+        assert_eq!(
+            humanize_relative_in(
+                &event,
+                Utc.ymd(2022, 4, 2).and_hms(22, 30, 0),
+                chrono_tz::Europe::Berlin
+            ),
+            "tomorrow"
+        );
+        assert_eq!(
+            relative(Utc.ymd(2022, 4, 7).and_hms(10, 0, 0)),
+            "3 days ago"
+        );
+        assert_eq!(
+            relative(Utc.ymd(2022, 3, 20).and_hms(10, 0, 0)),
+            "in 2 weeks"
+        );
+        assert_eq!(
+            relative(Utc.ymd(2020, 1, 1).and_hms(10, 0, 0)),
+            "in 2 years"
+        );
+    }
+
+    #[test]
+    fn exports_json_lines() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let events: Vec<Event> = ["Meeting", "Lunch"]
+            .into_iter()
+            .map(|name| {
+                Event::new(
+                    name,
+                    Some("first line\nsecond line"),
+                    Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+                    None,
+                    &app,
+                )
+                .unwrap()
+            })
+            .collect();
+
+        let ndjson = format_events(&app, &events, Format::Ndjson).unwrap();
+        let lines: Vec<&str> = ndjson.lines().collect();
+
+        assert_eq!(lines.len(), 2);
+        for (line, event) in lines.iter().zip(&events) {
+            assert_eq!(&serde_json::from_str::<Event>(line).unwrap(), event);
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Parsing of dates and times written by humans
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+
+use crate::{
+    error::{CalendarError, Result},
+    utils,
+};
+
+/// Parses a date and time like `tomorrow 3pm`, `next monday at 9:30` or `in 2 hours`. Relative phrases are anchored
+/// to `now` and dates and times are interpreted in the timezone `tz`. Supported are:
+///
+/// - RFC 3339 date times (like `2022-04-04T10:00:00+02:00`)
+/// - `now` and `in <n> <minutes|hours|days|weeks>`
+/// - A day (`today`, `tomorrow`, `yesterday`, a weekday optionally preceded by `next`, or a date like `2022-04-04`)
+///   followed by an optional time (`15:00`, `3pm`, `3:30 pm`, `noon` or `midnight`, optionally preceded by `at`)
+///
+/// A weekday means the next day with that weekday after today. Without a day the time is on the current day, without
+/// a time the start of the day is used
+pub fn parse_datetime(input: &str, now: DateTime<Utc>, tz: Tz) -> Result<DateTime<Utc>> {
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(input.trim()) {
+        return Ok(date_time.with_timezone(&Utc));
+    }
+
+    let invalid = || CalendarError::InvalidDateTime(input.to_string());
+    let input = input.trim().to_lowercase();
+    let words: Vec<&str> = input.split_whitespace().collect();
+
+    match words.as_slice() {
+        ["now"] => return Ok(now),
+        ["in", amount, unit] => {
+            let amount: i64 = amount.parse().map_err(|_| invalid())?;
+            let unit_seconds = match unit.trim_end_matches('s') {
+                "minute" | "min" => 60,
+                "hour" => 60 * 60,
+                "day" => 24 * 60 * 60,
+                "week" => 7 * 24 * 60 * 60,
+                _ => return Err(invalid()),
+            };
+
+            // Huge amounts would overflow the duration or the resulting date
+            return amount
+                .checked_mul(unit_seconds)
+                .and_then(|seconds| seconds.checked_mul(1000))
+                .and_then(|milliseconds| {
+                    now.checked_add_signed(Duration::milliseconds(milliseconds))
+                })
+                .ok_or_else(invalid);
+        }
+        _ => {}
+    }
+
+    let today = now.with_timezone(&tz).date().naive_local();
+    let mut date = None;
+    let mut time = None;
+    let mut words = words.into_iter().peekable();
+
+    while let Some(word) = words.next() {
+        let parsed_date = match word {
+            "today" => Some(today),
+            "tomorrow" => Some(today.succ()),
+            "yesterday" => Some(today.pred()),
+            "next" => Some(next_weekday(
+                today,
+                words.next().and_then(parse_weekday).ok_or_else(invalid)?,
+            )),
+            _ => parse_weekday(word)
+                .map(|weekday| next_weekday(today, weekday))
+                .or_else(|| NaiveDate::parse_from_str(word, "%Y-%m-%d").ok()),
+        };
+
+        if let Some(parsed_date) = parsed_date {
+            if date.replace(parsed_date).is_some() {
+                return Err(invalid());
+            }
+
+            continue;
+        }
+
+        if word == "at" && time.is_none() {
+            continue;
+        }
+
+        // Allow a space between the time and am/pm
+        let word = match words.peek() {
+            Some(&suffix @ ("am" | "pm")) => {
+                words.next();
+                format!("{}{}", word, suffix)
+            }
+            _ => word.to_string(),
+        };
+
+        if time
+            .replace(parse_time(&word).ok_or_else(invalid)?)
+            .is_some()
+        {
+            return Err(invalid());
+        }
+    }
+
+    if date.is_none() && time.is_none() {
+        return Err(invalid());
+    }
+
+    let date = date.unwrap_or(today);
+    let time = time.unwrap_or_else(|| NaiveTime::from_hms(0, 0, 0));
+
+    Ok(utils::local_to_utc(&tz, date.and_time(time)))
+}
+
+/// Parses the (full or abbreviated) english name of a weekday
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    match word {
+        "monday" | "mon" => Some(Weekday::Mon),
+        "tuesday" | "tue" => Some(Weekday::Tue),
+        "wednesday" | "wed" => Some(Weekday::Wed),
+        "thursday" | "thu" => Some(Weekday::Thu),
+        "friday" | "fri" => Some(Weekday::Fri),
+        "saturday" | "sat" => Some(Weekday::Sat),
+        "sunday" | "sun" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// Returns the first day after `today` that is on the given weekday
+fn next_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let days = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+
+    today + Duration::days(if days == 0 { 7 } else { days.into() })
+}
+
+/// Parses a time of day like `15:00`, `3pm`, `3:30pm`, `noon` or `midnight`
+fn parse_time(word: &str) -> Option<NaiveTime> {
+    match word {
+        "noon" => return Some(NaiveTime::from_hms(12, 0, 0)),
+        "midnight" => return Some(NaiveTime::from_hms(0, 0, 0)),
+        _ => {}
+    }
+
+    let (clock, pm) = match (word.strip_suffix("am"), word.strip_suffix("pm")) {
+        (Some(clock), _) => (clock, Some(false)),
+        (_, Some(clock)) => (clock, Some(true)),
+        _ => (word, None),
+    };
+
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) => (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?),
+        // A bare number is only a time with am/pm, otherwise it's ambiguous
+        None if pm.is_some() => (clock.parse::<u32>().ok()?, 0),
+        None => return None,
+    };
+
+    let hour = match pm {
+        Some(pm) if (1..=12).contains(&hour) => hour % 12 + if pm { 12 } else { 0 },
+        Some(_) => return None,
+        None => hour,
+    };
+
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn parses_natural_language() {
+        // A monday
+        let now = Utc.ymd(2022, 4, 4).and_hms(10, 0, 0);
+        let parse = |input| parse_datetime(input, now, Tz::UTC).unwrap();
+
+        assert_eq!(parse("now"), now);
+        assert_eq!(parse("in 2 hours"), Utc.ymd(2022, 4, 4).and_hms(12, 0, 0));
+        assert_eq!(parse("in 1 week"), Utc.ymd(2022, 4, 11).and_hms(10, 0, 0));
+        assert_eq!(parse("tomorrow 3pm"), Utc.ymd(2022, 4, 5).and_hms(15, 0, 0));
+        assert_eq!(
+            parse("Tomorrow at 3:30 PM"),
+            Utc.ymd(2022, 4, 5).and_hms(15, 30, 0)
+        );
+        assert_eq!(parse("today"), Utc.ymd(2022, 4, 4).and_hms(0, 0, 0));
+        assert_eq!(parse("17:45"), Utc.ymd(2022, 4, 4).and_hms(17, 45, 0));
+        assert_eq!(parse("12am"), Utc.ymd(2022, 4, 4).and_hms(0, 0, 0));
+        assert_eq!(
+            parse("next monday noon"),
+            Utc.ymd(2022, 4, 11).and_hms(12, 0, 0)
+        );
+        assert_eq!(parse("fri 9:00"), Utc.ymd(2022, 4, 8).and_hms(9, 0, 0));
+        assert_eq!(
+            parse("2022-05-01 8am"),
+            Utc.ymd(2022, 5, 1).and_hms(8, 0, 0)
+        );
+        assert_eq!(
+            parse("2022-04-04T10:00:00+02:00"),
+            Utc.ymd(2022, 4, 4).and_hms(8, 0, 0)
+        );
+
+        for input in [
+            "",
+            "later",
+            "13pm",
+            "3",
+            "today tomorrow",
+            "in two days",
+            "next",
+            "in 99999999999999 weeks",
+            "in -99999999999999 weeks",
+            "in 9223372036854775807 minutes",
+            "in 999999999999 days",
+        ] {
+            assert!(matches!(
+                parse_datetime(input, now, Tz::UTC),
+                Err(CalendarError::InvalidDateTime(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn parses_in_timezone() {
+        // 23:00 UTC is already the next day in Berlin
+        let now = Utc.ymd(2022, 4, 4).and_hms(23, 0, 0);
+
+        assert_eq!(
+            parse_datetime("tomorrow 9am", now, Tz::Europe__Berlin).unwrap(),
+            Utc.ymd(2022, 4, 6).and_hms(7, 0, 0)
+        );
+    }
+}
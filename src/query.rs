@@ -0,0 +1,623 @@
+//! Functions for finding events matching certain criteria
+
+use std::{cmp::Reverse, collections::BTreeMap};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc, Weekday};
+use chrono_tz::Tz;
+
+use crate::{
+    error::Result,
+    event::{self, Event, EventStatus},
+    utils, App,
+};
+
+/// Returns all events that have the given tag. Tags are compared ignoring case and surrounding whitespace (see
+/// [Event::has_tag])
+pub fn events_with_tag<'a>(events: &'a [Event], tag: &str) -> Vec<&'a Event> {
+    events.iter().filter(|event| event.has_tag(tag)).collect()
+}
+
+/// Returns all events whose name or description contains `query`, ignoring case. The matches are sorted
+/// chronologically (by [Event::date_time], then by id). An empty query matches every event
+pub fn search_events<'a>(events: &'a [Event], query: &str) -> Vec<&'a Event> {
+    let query = query.to_lowercase();
+
+    let mut matches: Vec<&Event> = events
+        .iter()
+        .filter(|event| {
+            event.name.to_lowercase().contains(&query)
+                || event
+                    .description
+                    .as_ref()
+                    .is_some_and(|description| description.to_lowercase().contains(&query))
+        })
+        .collect();
+    matches.sort();
+
+    matches
+}
+
+/// Returns all events whose name is at most `max_distance` edits (insertions, deletions or substitutions of a
+/// character) away from `query`, ignoring case, together with their distance. The query is compared
+/// to the whole name and to each of its words, the closest one counts. The matches are sorted by distance (closest
+/// first), matches with the same distance are sorted chronologically
+pub fn search_fuzzy<'a>(
+    events: &'a [Event],
+    query: &str,
+    max_distance: usize,
+) -> Vec<(&'a Event, usize)> {
+    let query = query.trim().to_lowercase();
+
+    let mut matches: Vec<(&Event, usize)> = events
+        .iter()
+        .filter_map(|event| {
+            let name = event.name.to_lowercase();
+            let distance = name
+                .split_whitespace()
+                .chain(std::iter::once(name.as_str()))
+                .map(|candidate| levenshtein(&query, candidate))
+                .min()?;
+
+            (distance <= max_distance).then_some((event, distance))
+        })
+        .collect();
+    matches.sort_by_key(|&(event, distance)| (distance, event));
+
+    matches
+}
+
+/// Returns the Levenshtein distance between `a` and `b`, the minimum number of characters that have to be inserted,
+/// deleted or substituted to turn one into the other
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // The distances between the processed part of `a` and every prefix of `b`
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Sorts the events by [Event::priority] (highest first), events with the same priority are sorted chronologically (by
+/// [Event::date_time], then by id)
+pub fn sort_by_priority_then_time(events: &mut [Event]) {
+    events.sort_by_key(|event| (Reverse(event.priority), event.date_time, event.id()));
+}
+
+/// Returns all events in [App::events_dir] that happen on the given (local) day in the timezone `tz`, sorted like
+/// [event::read_events] (see [event::read_events_in_local_range], cancelled events are included). The day is bounded by
+/// local midnights, so days on which daylight saving time starts or ends (which are 23 or 25 hours long) are handled
+/// correctly
+pub fn events_on_day(app: &App, date: NaiveDate, tz: Tz) -> Result<Vec<Event>> {
+    event::read_events_in_local_range(
+        app,
+        date.and_hms(0, 0, 0),
+        date.succ().and_hms(0, 0, 0),
+        Some(tz),
+        true,
+    )
+}
+
+/// Groups the events by the (local) day in the timezone `tz` on which they happen (the day of [Event::date_time]).
+/// The events of each day are sorted chronologically
+pub fn group_by_day(events: &[Event], tz: Tz) -> BTreeMap<NaiveDate, Vec<Event>> {
+    let mut days: BTreeMap<NaiveDate, Vec<Event>> = BTreeMap::new();
+
+    for event in events {
+        let date = event.date_time.with_timezone(&tz).date().naive_local();
+        days.entry(date).or_default().push(event.clone());
+    }
+
+    for events in days.values_mut() {
+        events.sort();
+    }
+
+    days
+}
+
+/// Returns the events in [App::events_dir] of the week containing `anchor` in the timezone `tz`, one list per day
+/// starting with `week_starts_on` (so index 0 is the first day of the week), each sorted chronologically. Like
+/// [events_on_day], days are bounded by local midnights and cancelled events are included. Events that last longer than
+/// a day (all-day or not) are listed on every day of the week they cover, including those that started before the week
+pub fn week_view(
+    app: &App,
+    anchor: NaiveDate,
+    tz: Tz,
+    week_starts_on: Weekday,
+) -> Result<[Vec<Event>; 7]> {
+    let offset =
+        (anchor.weekday().num_days_from_monday() + 7 - week_starts_on.num_days_from_monday()) % 7;
+    let first_day = anchor - Duration::days(offset.into());
+    // The local midnights at the start of each day and at the end of the week
+    let midnights: Vec<DateTime<Utc>> = (0..=7)
+        .map(|day| utils::local_to_utc(&tz, (first_day + Duration::days(day)).and_hms(0, 0, 0)))
+        .collect();
+
+    let mut days: [Vec<Event>; 7] = Default::default();
+
+    for event in event::read_events_in_range(app, midnights[0], midnights[7], true)? {
+        for (day, bounds) in midnights.windows(2).enumerate() {
+            if event.in_range(bounds[0], bounds[1]) {
+                days[day].push(event.clone());
+            }
+        }
+    }
+
+    Ok(days)
+}
+
+/// Returns the earliest event in [App::events_dir] that happens at or after `now` (an event exactly at `now` counts as
+/// upcoming), or `None` if there is no such event. Only [Event::date_time] is considered, recurrences aren't expanded.
+/// Cancelled events (see [EventStatus]) are skipped unless `include_cancelled` is `true`
+pub fn next_event(app: &App, now: DateTime<Utc>, include_cancelled: bool) -> Result<Option<Event>> {
+    // read_events returns the events sorted chronologically
+    Ok(event::read_events(app)?.into_iter().find(|event| {
+        event.date_time >= now && (include_cancelled || event.status != EventStatus::Cancelled)
+    }))
+}
+
+/// Returns the events in [App::events_dir] that are in progress at `now` (see [Event::contains_time]), sorted by
+/// [Event::start]. Unlike [next_event], events that started before `now` are included, but those that haven't started
+/// yet aren't. Cancelled events (see [EventStatus]) are skipped, recurrences aren't expanded
+pub fn ongoing(app: &App, now: DateTime<Utc>) -> Result<Vec<Event>> {
+    let mut events = event::read_events_in_range(app, now, now + Duration::nanoseconds(1), false)?;
+    events.retain(|event| event.contains_time(now));
+    events.sort_by_key(|event| (event.start(), event.id()));
+
+    Ok(events)
+}
+
+/// Returns the event whose [Event::date_time] is closest to `to`, in either direction, or `None` if there are no
+/// events. If a past and a future event are equally far away, the future one wins (an event exactly at `to` counts as
+/// future), events at the same time are compared by id
+pub fn nearest(events: &[Event], to: DateTime<Utc>) -> Option<&Event> {
+    events.iter().min_by_key(|event| {
+        let past = event.date_time < to;
+        let distance = if past {
+            to - event.date_time
+        } else {
+            event.date_time - to
+        };
+
+        (distance, past, *event)
+    })
+}
+
+/// Returns the free time slots between `day_start` and `day_end` that are at least `min_len` long, as `(start, end)`
+/// pairs in chronological order. Events are busy from [Event::start] to [Event::end] (overlapping events are merged),
+/// so only events with a duration (an end time, or all-day events) block time. Cancelled events (see [EventStatus])
+/// don't block anything, recurrences are not expanded
+pub fn free_slots(
+    events: &[Event],
+    day_start: DateTime<Utc>,
+    day_end: DateTime<Utc>,
+    min_len: Duration,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let mut busy: Vec<(DateTime<Utc>, DateTime<Utc>)> = events
+        .iter()
+        .filter(|event| event.status != EventStatus::Cancelled && !event.duration().is_zero())
+        .map(|event| (event.start(), event.end()))
+        .collect();
+    busy.sort();
+
+    let mut slots = Vec::new();
+    // The end of the busy time so far, everything before it is taken
+    let mut free_from = day_start;
+
+    for (start, end) in busy {
+        if start >= day_end {
+            break;
+        }
+
+        if start > free_from && start - free_from >= min_len {
+            slots.push((free_from, start));
+        }
+        free_from = free_from.max(end);
+    }
+
+    if day_end > free_from && day_end - free_from >= min_len {
+        slots.push((free_from, day_end));
+    }
+
+    slots
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::{event::Priority, App};
+
+    #[test]
+    fn filters_events_by_tag() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let date_time = Utc.ymd(2022, 4, 4).and_hms(10, 0, 0);
+        let work = Event::new("Meeting", None, date_time, None, &app)
+            .unwrap()
+            .with_tags(&[" Work ", "important"]);
+        let gym = Event::new("Gym", None, date_time, None, &app)
+            .unwrap()
+            .with_tags(&["health"]);
+        let events = vec![work.clone(), gym];
+
+        assert_eq!(work.tags, vec!["Work", "important"]);
+        assert_eq!(events_with_tag(&events, "work "), vec![&work]);
+        assert_eq!(events_with_tag(&events, "WORK"), vec![&work]);
+        assert!(events_with_tag(&events, "travel").is_empty());
+    }
+
+    #[test]
+    fn searches_names_and_descriptions() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let later = Event::new(
+            "Dentist",
+            Some("Bring insurance CARD"),
+            Utc.ymd(2022, 5, 1).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        let earlier = Event::new(
+            "Card game night",
+            None,
+            Utc.ymd(2022, 4, 1).and_hms(20, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        let other = Event::new(
+            "Gym",
+            None,
+            Utc.ymd(2022, 3, 1).and_hms(8, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        let events = vec![later.clone(), other.clone(), earlier.clone()];
+
+        assert_eq!(search_events(&events, "card"), vec![&earlier, &later]);
+        assert_eq!(search_events(&events, "").len(), 3);
+        assert!(search_events(&events, "cinema").is_empty());
+    }
+
+    #[test]
+    fn searches_names_fuzzily() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = |name, day| {
+            Event::new(
+                name,
+                None,
+                Utc.ymd(2022, 4, day).and_hms(10, 0, 0),
+                None,
+                &app,
+            )
+            .unwrap()
+        };
+        let dentist = event("Dentist", 3);
+        let dentist_later = event("Dentist appointment", 4);
+        let gym = event("Gym", 1);
+        let events = vec![dentist_later.clone(), gym, dentist.clone()];
+
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(
+            search_fuzzy(&events, "Dentsit", 2),
+            vec![(&dentist, 2), (&dentist_later, 2)]
+        );
+        assert_eq!(
+            search_fuzzy(&events, "dentist", 0),
+            vec![(&dentist, 0), (&dentist_later, 0)]
+        );
+        assert_eq!(
+            search_fuzzy(&events, "appointmnt", 1),
+            vec![(&dentist_later, 1)]
+        );
+        assert!(search_fuzzy(&events, "cinema", 2).is_empty());
+    }
+
+    #[test]
+    fn sorts_by_priority_then_time() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = |name, hour, priority| {
+            Event::new(
+                name,
+                None,
+                Utc.ymd(2022, 4, 4).and_hms(hour, 0, 0),
+                None,
+                &app,
+            )
+            .unwrap()
+            .with_priority(priority)
+        };
+
+        let mut events = vec![
+            event("Low", 8, Priority::Low),
+            event("Normal later", 12, Priority::Normal),
+            event("Normal", 10, Priority::Normal),
+            event("High", 10, Priority::High),
+        ];
+        sort_by_priority_then_time(&mut events);
+
+        let names: Vec<&str> = events.iter().map(|event| event.name.as_str()).collect();
+        assert_eq!(names, vec!["High", "Normal", "Normal later", "Low"]);
+    }
+
+    #[test]
+    fn finds_events_on_day() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        // Daylight saving time starts on 2022-03-27 in Berlin, so that day is only 23 hours long (22:00 UTC on the 26th
+        // to 22:00 UTC on the 27th)
+        let mut events = Vec::new();
+
+        for (name, date_time) in [
+            ("Day before", Utc.ymd(2022, 3, 26).and_hms(21, 59, 0)),
+            ("Midnight", Utc.ymd(2022, 3, 26).and_hms(23, 0, 0)),
+            ("Evening", Utc.ymd(2022, 3, 27).and_hms(21, 30, 0)),
+            ("Day after", Utc.ymd(2022, 3, 27).and_hms(22, 0, 0)),
+        ] {
+            let event = Event::new(name, None, date_time, None, &app).unwrap();
+            event.store(&app).unwrap();
+            events.push(event);
+        }
+
+        let day =
+            events_on_day(&app, NaiveDate::from_ymd(2022, 3, 27), Tz::Europe__Berlin).unwrap();
+        assert_eq!(day, events[1..3]);
+
+        let day = events_on_day(&app, NaiveDate::from_ymd(2022, 3, 27), Tz::UTC).unwrap();
+        assert_eq!(day, events[2..]);
+    }
+
+    #[test]
+    fn groups_events_by_day() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = |name, date_time| Event::new(name, None, date_time, None, &app).unwrap();
+        let late = event("Late", Utc.ymd(2022, 4, 4).and_hms(22, 30, 0));
+        let early = event("Early", Utc.ymd(2022, 4, 4).and_hms(8, 0, 0));
+        let next_day = event("Next day", Utc.ymd(2022, 4, 5).and_hms(8, 0, 0));
+        let events = vec![next_day.clone(), late.clone(), early.clone()];
+
+        let days = group_by_day(&events, Tz::UTC);
+        assert_eq!(
+            days.into_iter().collect::<Vec<_>>(),
+            vec![
+                (
+                    NaiveDate::from_ymd(2022, 4, 4),
+                    vec![early.clone(), late.clone()]
+                ),
+                (NaiveDate::from_ymd(2022, 4, 5), vec![next_day.clone()]),
+            ]
+        );
+
+        // 22:30 UTC is already the next day in Berlin
+        let days = group_by_day(&events, Tz::Europe__Berlin);
+        assert_eq!(days[&NaiveDate::from_ymd(2022, 4, 4)], vec![early]);
+        assert_eq!(days[&NaiveDate::from_ymd(2022, 4, 5)], vec![late, next_day]);
+    }
+
+    #[test]
+    fn builds_week_view() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        // 2022-04-03 is a Sunday, 2022-04-04 a Monday
+        let mut events = Vec::new();
+
+        for (name, date_time) in [
+            ("Sunday", Utc.ymd(2022, 4, 3).and_hms(10, 0, 0)),
+            ("Monday late", Utc.ymd(2022, 4, 4).and_hms(18, 0, 0)),
+            ("Monday", Utc.ymd(2022, 4, 4).and_hms(9, 0, 0)),
+            ("Next Sunday", Utc.ymd(2022, 4, 10).and_hms(10, 0, 0)),
+        ] {
+            let event = Event::new(name, None, date_time, None, &app).unwrap();
+            event.store(&app).unwrap();
+            events.push(event);
+        }
+
+        // Starts before the week and ends on its Tuesday, ending at midnight doesn't count for Wednesday
+        Event::new(
+            "Trip",
+            None,
+            Utc.ymd(2022, 4, 2).and_hms(8, 0, 0),
+            Some(Utc.ymd(2022, 4, 6).and_hms(0, 0, 0)),
+            &app,
+        )
+        .unwrap()
+        .store(&app)
+        .unwrap();
+
+        let names = |days: &[Vec<Event>; 7]| -> Vec<Vec<String>> {
+            days.iter()
+                .map(|day| day.iter().map(|event| event.name.clone()).collect())
+                .collect()
+        };
+
+        let anchor = NaiveDate::from_ymd(2022, 4, 6);
+        let week = week_view(&app, anchor, Tz::UTC, Weekday::Mon).unwrap();
+        assert_eq!(
+            names(&week),
+            vec![
+                vec!["Trip", "Monday", "Monday late"],
+                vec!["Trip"],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec!["Next Sunday"],
+            ]
+        );
+
+        let week = week_view(&app, anchor, Tz::UTC, Weekday::Sun).unwrap();
+        assert_eq!(names(&week)[0], vec!["Trip", "Sunday"]);
+        assert_eq!(names(&week)[1], vec!["Trip", "Monday", "Monday late"]);
+        assert_eq!(names(&week)[2], vec!["Trip"]);
+        assert!(names(&week)[3..].iter().all(Vec::is_empty));
+
+        // The anchor itself can be the first day of the week
+        let week = week_view(&app, NaiveDate::from_ymd(2022, 4, 3), Tz::UTC, Weekday::Sun).unwrap();
+        assert_eq!(names(&week)[0], vec!["Trip", "Sunday"]);
+    }
+
+    #[test]
+    fn finds_next_event() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let now = Utc.ymd(2022, 4, 4).and_hms(12, 0, 0);
+        assert_eq!(next_event(&app, now, true).unwrap(), None);
+
+        let mut events = Vec::new();
+
+        for (name, hour) in [("Past", 11), ("Now", 12), ("Later", 13)] {
+            let event = Event::new(
+                name,
+                None,
+                Utc.ymd(2022, 4, 4).and_hms(hour, 0, 0),
+                None,
+                &app,
+            )
+            .unwrap();
+            event.store(&app).unwrap();
+            events.push(event);
+        }
+
+        assert_eq!(
+            next_event(&app, now, true).unwrap().as_ref(),
+            Some(&events[1])
+        );
+        assert_eq!(
+            next_event(&app, now + chrono::Duration::hours(2), true).unwrap(),
+            None
+        );
+
+        events[1].set_status(&app, EventStatus::Cancelled).unwrap();
+        assert_eq!(
+            next_event(&app, now, true).unwrap().as_ref(),
+            Some(&events[1])
+        );
+        assert_eq!(
+            next_event(&app, now, false).unwrap().as_ref(),
+            Some(&events[2])
+        );
+    }
+
+    #[test]
+    fn finds_free_slots() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let at = |hour, minute| Utc.ymd(2022, 4, 4).and_hms(hour, minute, 0);
+        let event = |start, end| Event::new("Busy", None, start, Some(end), &app).unwrap();
+        let mut cancelled = event(at(15, 0), at(16, 0));
+        cancelled.status = EventStatus::Cancelled;
+        let events = [
+            event(at(10, 0), at(11, 0)),
+            // Overlaps the previous event
+            event(at(10, 30), at(12, 0)),
+            event(at(12, 10), at(13, 0)),
+            Event::new("Point", None, at(14, 0), None, &app).unwrap(),
+            cancelled,
+            event(at(16, 30), at(18, 0)),
+        ];
+
+        assert_eq!(
+            free_slots(&events, at(9, 0), at(17, 0), Duration::minutes(30)),
+            vec![(at(9, 0), at(10, 0)), (at(13, 0), at(16, 30))]
+        );
+        assert_eq!(
+            free_slots(&events, at(9, 0), at(17, 0), Duration::minutes(10)),
+            vec![
+                (at(9, 0), at(10, 0)),
+                (at(12, 0), at(12, 10)),
+                (at(13, 0), at(16, 30))
+            ]
+        );
+        assert_eq!(
+            free_slots(&[], at(9, 0), at(17, 0), Duration::hours(1)),
+            vec![(at(9, 0), at(17, 0))]
+        );
+        assert!(free_slots(&events, at(10, 15), at(11, 45), Duration::zero()).is_empty());
+    }
+
+    #[test]
+    fn finds_ongoing_events() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let at = |hour| Utc.ymd(2022, 4, 4).and_hms(hour, 0, 0);
+        let now = at(12);
+        let event = |name, start, end| {
+            let event = Event::new(name, None, start, end, &app).unwrap();
+            event.store(&app).unwrap();
+            event
+        };
+        let workshop = event("Workshop", at(9), Some(at(17)));
+        let lunch = event("Lunch", at(12), Some(at(13)));
+        event("Ended", at(11), Some(at(12)));
+        event("Later", at(13), Some(at(14)));
+        let point = event("Point", now, None);
+        event("Other point", at(11), None);
+        let holiday = Event::new("Holiday", None, at(10), None, &app)
+            .unwrap()
+            .with_all_day(true);
+        holiday.store(&app).unwrap();
+        let mut cancelled = event("Cancelled", at(10), Some(at(14)));
+        cancelled.status = EventStatus::Cancelled;
+        cancelled.store(&app).unwrap();
+
+        assert_eq!(
+            ongoing(&app, now).unwrap(),
+            vec![holiday.clone(), workshop, lunch, point]
+        );
+        assert_eq!(ongoing(&app, at(18)).unwrap(), vec![holiday]);
+    }
+
+    #[test]
+    fn finds_nearest_event() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let at = |hour| Utc.ymd(2022, 4, 4).and_hms(hour, 0, 0);
+        let event = |hour| Event::new("Event Name", None, at(hour), None, &app).unwrap();
+        let events = vec![event(8), event(11), event(13), event(17)];
+
+        assert_eq!(nearest(&events, at(10)), Some(&events[1]));
+        assert_eq!(nearest(&events, at(20)), Some(&events[3]));
+        // Equally far from 11:00 and 13:00
+        assert_eq!(nearest(&events, at(12)), Some(&events[2]));
+        assert_eq!(nearest(&[], at(12)), None);
+    }
+}
@@ -0,0 +1,283 @@
+//! Recurrence rules for events that repeat (similar to a simplified iCalendar `RRULE`)
+
+use std::cmp::Ordering;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::utils;
+
+/// How often a recurring event repeats
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Frequency {
+    /// Every day
+    Daily,
+    /// Every week on the given weekday
+    Weekly { weekday: Weekday },
+    /// Every month on the given day of the month. If a month is shorter than `day` the event occurs on the last day of
+    /// that month instead (so a monthly event on the 31st occurs on the 28th/29th in February)
+    Monthly { day: u32 },
+}
+
+impl Frequency {
+    /// The variant and its field as numbers, for comparing frequencies
+    fn key(&self) -> (u8, u32) {
+        match self {
+            Frequency::Daily => (0, 0),
+            Frequency::Weekly { weekday } => (1, weekday.num_days_from_monday()),
+            Frequency::Monthly { day } => (2, *day),
+        }
+    }
+}
+
+/// Frequencies are ordered from daily to monthly, weekly ones by their weekday (starting on Monday) and monthly ones
+/// by their day
+impl Ord for Frequency {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl PartialOrd for Frequency {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Describes how an event repeats. Occurrences always happen at the (local) time of day of the event and never before
+/// the event itself
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Recurrence {
+    /// How often the event repeats
+    pub frequency: Frequency,
+    /// The last point in time at which an occurrence may happen (inclusive). Repeats forever if `None`
+    pub until: Option<DateTime<Utc>>,
+    /// Occurrences that are skipped (like iCalendar's `EXDATE`), e.g. a weekly meeting that is cancelled on a holiday.
+    /// An exception matches an occurrence at the same local date and time in the timezone of the event
+    #[serde(default)]
+    pub exceptions: Vec<DateTime<Utc>>,
+}
+
+impl Recurrence {
+    pub fn new(frequency: Frequency, until: Option<DateTime<Utc>>) -> Self {
+        Self {
+            frequency,
+            until,
+            exceptions: Vec::new(),
+        }
+    }
+
+    /// Sets the occurrences that are skipped (see [Recurrence::exceptions])
+    pub fn with_exceptions(mut self, exceptions: &[DateTime<Utc>]) -> Self {
+        self.exceptions = exceptions.to_vec();
+        self
+    }
+
+    /// Expands the recurrence of an event starting at `first` into all occurrences within `[start, end)`, in
+    /// chronological order. The dates and times of the occurrences are calculated in the timezone of `first`, so an
+    /// event at 09:00 stays at 09:00 local time when daylight saving time starts or ends. Occurrences matching one of
+    /// the [Recurrence::exceptions] are left out
+    pub fn occurrences<T: TimeZone>(
+        &self,
+        first: DateTime<T>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Vec<DateTime<Utc>> {
+        let mut occurrences = Vec::new();
+
+        let tz = first.timezone();
+        let time = first.time();
+        let first_date = first.naive_local().date();
+        let first = first.with_timezone(&Utc);
+        let exceptions: Vec<NaiveDateTime> = self
+            .exceptions
+            .iter()
+            .map(|exception| exception.with_timezone(&tz).naive_local())
+            .collect();
+        // Occurrences before this date can't be inside the range (or happen before the event itself)
+        let from = first_date.max(start.with_timezone(&tz).naive_local().date());
+
+        let mut push = |date: NaiveDate| -> bool {
+            let occurrence = utils::local_to_utc(&tz, date.and_time(time));
+
+            if occurrence >= end || self.until.is_some_and(|until| occurrence > until) {
+                return false;
+            }
+
+            if occurrence >= start
+                && occurrence >= first
+                && !exceptions.contains(&occurrence.with_timezone(&tz).naive_local())
+            {
+                occurrences.push(occurrence);
+            }
+
+            true
+        };
+
+        match self.frequency {
+            Frequency::Daily => {
+                let mut date = from;
+
+                while push(date) {
+                    date = date.succ();
+                }
+            }
+            Frequency::Weekly { weekday } => {
+                let days_until_weekday = (7 + weekday.num_days_from_monday()
+                    - from.weekday().num_days_from_monday())
+                    % 7;
+                let mut date = from + Duration::days(days_until_weekday.into());
+
+                while push(date) {
+                    date += Duration::weeks(1);
+                }
+            }
+            Frequency::Monthly { day } => {
+                let (mut year, mut month) = (from.year(), from.month());
+
+                while push(day_of_month(year, month, day)) {
+                    if month == 12 {
+                        year += 1;
+                        month = 1;
+                    } else {
+                        month += 1;
+                    }
+                }
+            }
+        }
+
+        occurrences
+    }
+}
+
+/// Returns the given day of the month, clamped to the last day of that month
+fn day_of_month(year: i32, month: u32, day: u32) -> NaiveDate {
+    (1..=day.clamp(1, 31))
+        .rev()
+        .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+        .expect("every month has at least 28 days")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_weekly_recurrences() {
+        // 2022-04-04 is a monday
+        let first = Utc.ymd(2022, 4, 4).and_hms(9, 0, 0);
+        let recurrence = Recurrence::new(
+            Frequency::Weekly {
+                weekday: Weekday::Wed,
+            },
+            None,
+        );
+
+        let occurrences = recurrence.occurrences(
+            first,
+            Utc.ymd(2022, 4, 1).and_hms(0, 0, 0),
+            Utc.ymd(2022, 4, 20).and_hms(9, 0, 0),
+        );
+
+        assert_eq!(
+            occurrences,
+            vec![
+                Utc.ymd(2022, 4, 6).and_hms(9, 0, 0),
+                Utc.ymd(2022, 4, 13).and_hms(9, 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn monthly_recurrences_are_clamped_to_month_end() {
+        let first = Utc.ymd(2024, 1, 31).and_hms(12, 0, 0);
+        let recurrence = Recurrence::new(Frequency::Monthly { day: 31 }, None);
+
+        let occurrences = recurrence.occurrences(
+            first,
+            Utc.ymd(2024, 1, 1).and_hms(0, 0, 0),
+            Utc.ymd(2024, 5, 1).and_hms(0, 0, 0),
+        );
+
+        assert_eq!(
+            occurrences,
+            vec![
+                Utc.ymd(2024, 1, 31).and_hms(12, 0, 0),
+                Utc.ymd(2024, 2, 29).and_hms(12, 0, 0),
+                Utc.ymd(2024, 3, 31).and_hms(12, 0, 0),
+                Utc.ymd(2024, 4, 30).and_hms(12, 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn recurrences_stop_at_until() {
+        let first = Utc.ymd(2022, 1, 1).and_hms(8, 0, 0);
+        let recurrence =
+            Recurrence::new(Frequency::Daily, Some(Utc.ymd(2022, 1, 3).and_hms(8, 0, 0)));
+
+        let occurrences = recurrence.occurrences(
+            first,
+            Utc.ymd(2021, 1, 1).and_hms(0, 0, 0),
+            Utc.ymd(2023, 1, 1).and_hms(0, 0, 0),
+        );
+
+        assert_eq!(occurrences.len(), 3);
+    }
+
+    #[test]
+    fn recurrences_keep_local_time_across_dst() {
+        use chrono_tz::Europe::Berlin;
+
+        // Daylight saving time started in Berlin on 2022-03-27
+        let first = Berlin.ymd(2022, 3, 21).and_hms(9, 0, 0);
+        let recurrence = Recurrence::new(
+            Frequency::Weekly {
+                weekday: Weekday::Mon,
+            },
+            None,
+        );
+
+        let occurrences = recurrence.occurrences(
+            first,
+            Utc.ymd(2022, 3, 1).and_hms(0, 0, 0),
+            Utc.ymd(2022, 4, 1).and_hms(0, 0, 0),
+        );
+
+        assert_eq!(
+            occurrences,
+            vec![
+                Utc.ymd(2022, 3, 21).and_hms(8, 0, 0),
+                Utc.ymd(2022, 3, 28).and_hms(7, 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_exceptions() {
+        use chrono_tz::Europe::Berlin;
+
+        // A weekly standup at 09:00 in Berlin, which is 08:00 UTC in winter and 07:00 UTC in summer
+        let first = Berlin.ymd(2022, 3, 21).and_hms(9, 0, 0);
+        let recurrence = Recurrence::new(
+            Frequency::Weekly {
+                weekday: Weekday::Mon,
+            },
+            None,
+        );
+        let start = Utc.ymd(2022, 3, 1).and_hms(0, 0, 0);
+        let end = Utc.ymd(2022, 4, 12).and_hms(0, 0, 0);
+
+        let occurrences = recurrence.occurrences(first, start, end);
+        assert_eq!(occurrences.len(), 4);
+
+        // Easter Monday, after daylight saving time started
+        let holiday = Berlin.ymd(2022, 4, 4).and_hms(9, 0, 0).with_timezone(&Utc);
+        let recurrence =
+            recurrence.with_exceptions(&[holiday, Utc.ymd(2022, 4, 11).and_hms(8, 0, 0)]);
+        let without_holiday = recurrence.occurrences(first, start, end);
+        assert_eq!(without_holiday.len(), 3);
+        assert!(!without_holiday.contains(&holiday));
+        assert!(without_holiday.contains(&Utc.ymd(2022, 4, 11).and_hms(7, 0, 0)));
+    }
+}
@@ -0,0 +1,180 @@
+//! Reminders of upcoming events
+
+use std::{collections::BTreeSet, fs, io};
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{
+    error::{Context, Result},
+    event::{self, Event},
+    App,
+};
+
+/// The name of the file in [App::events_dir] that stores which reminders have already fired (see
+/// [take_due_reminders])
+pub const FIRED_REMINDERS_FILE: &str = ".fired_reminders";
+
+/// A reminder that fired, identified by the id of its event and the time it fired for
+type FiredReminder = (u128, DateTime<Utc>);
+
+/// Returns the reminders (see [Event::reminders]) that are due within `[now, now + window)`, together with the time at
+/// which each of them fires, sorted by that time. Only the first occurrence of recurring events is considered
+pub fn due_reminders(
+    events: &[Event],
+    now: DateTime<Utc>,
+    window: Duration,
+) -> Vec<(&Event, DateTime<Utc>)> {
+    let end = now + window;
+
+    let mut due: Vec<(&Event, DateTime<Utc>)> = events
+        .iter()
+        .flat_map(|event| {
+            event
+                .reminders
+                .iter()
+                .map(move |reminder| (event, event.start() - *reminder))
+        })
+        .filter(|(_, fires_at)| *fires_at >= now && *fires_at < end)
+        .collect();
+    due.sort_by_key(|(event, fires_at)| (*fires_at, event.id()));
+
+    due
+}
+
+/// Like [due_reminders], but reminders that were already returned by an earlier call (also by another process) are
+/// skipped, so each reminder is only returned once. The returned reminders are recorded as fired in
+/// [FIRED_REMINDERS_FILE]. Reminders are identified by the time they fire, so rescheduling an event (or changing its
+/// reminders) makes its reminders fire again for the new time. Records of reminders that fired before `now` are
+/// dropped, since those can't be due again
+pub fn take_due_reminders<'a>(
+    app: &App,
+    events: &'a [Event],
+    now: DateTime<Utc>,
+    window: Duration,
+) -> Result<Vec<(&'a Event, DateTime<Utc>)>> {
+    let _lock = event::lock(app)?;
+    let path = app.events_dir.join(FIRED_REMINDERS_FILE);
+
+    let mut fired: BTreeSet<FiredReminder> = match fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).with_context(|| {
+            format!(
+                "failed to deserialize fired reminders from `{}`",
+                path.to_string_lossy()
+            )
+        })?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeSet::new(),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("failed to read from file `{}`", path.to_string_lossy()))
+        }
+    };
+
+    let due: Vec<(&Event, DateTime<Utc>)> = due_reminders(events, now, window)
+        .into_iter()
+        .filter(|(event, fires_at)| fired.insert((event.id(), *fires_at)))
+        .collect();
+    fired.retain(|(_, fires_at)| *fires_at >= now);
+
+    let json = serde_json::to_vec(&fired).with_context(|| "failed to serialize fired reminders")?;
+    event::write_atomic(&path, &json)?;
+
+    Ok(due)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::App;
+
+    #[test]
+    fn finds_due_reminders() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let meeting = Event::new(
+            "Meeting",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap()
+        .with_reminders(&[Duration::minutes(30), Duration::days(1)]);
+        let lunch = Event::new(
+            "Lunch",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(12, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap()
+        .with_reminders(&[Duration::hours(2), Duration::zero()]);
+        let events = vec![lunch.clone(), meeting.clone()];
+
+        let now = Utc.ymd(2022, 4, 4).and_hms(9, 0, 0);
+        assert_eq!(
+            due_reminders(&events, now, Duration::hours(1)),
+            vec![(&meeting, Utc.ymd(2022, 4, 4).and_hms(9, 30, 0))]
+        );
+        assert_eq!(
+            due_reminders(&events, now, Duration::hours(3)),
+            vec![
+                (&meeting, Utc.ymd(2022, 4, 4).and_hms(9, 30, 0)),
+                (&lunch, Utc.ymd(2022, 4, 4).and_hms(10, 0, 0)),
+            ]
+        );
+
+        // Reminders after the start of the event are rejected
+        let invalid = meeting.clone().with_reminders(&[-Duration::minutes(5)]);
+        assert!(invalid.store(&app).is_err());
+
+        meeting.store(&app).unwrap();
+        assert_eq!(Event::load(&app, meeting.id()).unwrap(), meeting);
+    }
+
+    #[test]
+    fn takes_due_reminders_only_once() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let mut meeting = Event::new(
+            "Meeting",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap()
+        .with_reminders(&[Duration::minutes(30)]);
+        meeting.store(&app).unwrap();
+
+        let now = Utc.ymd(2022, 4, 4).and_hms(9, 0, 0);
+        let window = Duration::hours(1);
+        let fires_at = Utc.ymd(2022, 4, 4).and_hms(9, 30, 0);
+
+        let events = vec![meeting.clone()];
+        assert_eq!(
+            take_due_reminders(&app, &events, now, window).unwrap(),
+            vec![(&meeting, fires_at)]
+        );
+        // The fired state is persisted, so it survives restarts
+        assert!(app.events_dir.join(FIRED_REMINDERS_FILE).is_file());
+        assert!(take_due_reminders(&app, &events, now, window)
+            .unwrap()
+            .is_empty());
+        assert_eq!(event::read_events(&app).unwrap(), events);
+
+        // Rescheduling the event makes the reminder fire again
+        meeting =
+            event::move_event(&app, meeting.id(), Utc.ymd(2022, 4, 4).and_hms(9, 45, 0)).unwrap();
+        let events = vec![meeting.clone()];
+        assert_eq!(
+            take_due_reminders(&app, &events, now, window).unwrap(),
+            vec![(&meeting, Utc.ymd(2022, 4, 4).and_hms(9, 15, 0))]
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! A stable json representation of events for other programs, independent of how events are stored (see
+//! [Event::to_json] and [Event::from_json])
+
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{CalendarError, Context, Result},
+    event::{Event, EventStatus, Priority},
+    recurrence::Recurrence,
+};
+
+/// The version of the json representation created by [Event::to_json]. It's increased whenever a field is renamed,
+/// removed or changes its meaning, adding fields doesn't change it
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The json representation of an event. The fields are serialized in this order
+#[derive(Serialize, Deserialize)]
+struct EventJson {
+    schema_version: u32,
+    id: u128,
+    name: String,
+    description: Option<String>,
+    location: Option<String>,
+    attendees: Vec<String>,
+    start: DateTime<Utc>,
+    end: Option<DateTime<Utc>>,
+    all_day: bool,
+    timezone: Tz,
+    recurrence: Option<Recurrence>,
+    tags: Vec<String>,
+    priority: Priority,
+    status: EventStatus,
+    color: Option<String>,
+    /// How many seconds before the start of the event each reminder fires
+    reminders: Vec<i64>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    #[serde(default)]
+    url: Option<String>,
+}
+
+impl Event {
+    /// Serializes the event into its stable json representation (see [SCHEMA_VERSION]). Unlike the stored files, the
+    /// field names and their order don't change when the event struct changes. Fails if the event is outside of the
+    /// supported years (see [crate::event::MIN_YEAR] and [crate::event::MAX_YEAR])
+    pub fn to_json(&self) -> Result<String> {
+        self.validate_years()?;
+
+        let json = EventJson {
+            schema_version: SCHEMA_VERSION,
+            id: self.id(),
+            name: self.name.clone(),
+            description: self.description.clone(),
+            location: self.location.clone(),
+            attendees: self.attendees.clone(),
+            start: self.date_time,
+            end: self.end_time,
+            all_day: self.all_day,
+            timezone: self.tz,
+            recurrence: self.recurrence.clone(),
+            tags: self.tags.clone(),
+            priority: self.priority,
+            status: self.status,
+            color: self.color.clone(),
+            reminders: self.reminders.iter().map(Duration::num_seconds).collect(),
+            created_at: self.created_at(),
+            updated_at: self.updated_at(),
+            url: self.url.clone(),
+        };
+
+        serde_json::to_string_pretty(&json).with_context(|| {
+            format!(
+                "failed to serialize event `{}` (id: {})",
+                self.name,
+                self.id()
+            )
+        })
+    }
+
+    /// Deserializes an event from the json representation created by [Event::to_json]. Fails if the json was created
+    /// with another [SCHEMA_VERSION] or the event is invalid. The event isn't stored
+    pub fn from_json(json: &str) -> Result<Self> {
+        let json: EventJson =
+            serde_json::from_str(json).with_context(|| "failed to deserialize event")?;
+
+        if json.schema_version != SCHEMA_VERSION {
+            return Err(CalendarError::InvalidEvent(format!(
+                "unsupported schema version {} (expected {})",
+                json.schema_version, SCHEMA_VERSION
+            )));
+        }
+
+        let mut event = Event::unsaved(
+            &json.name,
+            json.description.as_deref(),
+            json.start,
+            json.end,
+            json.created_at,
+        )?
+        .with_attendees(&json.attendees)
+        .with_tags(&json.tags)
+        .with_priority(json.priority)
+        .with_timezone(json.timezone)
+        .with_all_day(json.all_day)
+        .with_reminders(
+            &json
+                .reminders
+                .iter()
+                .map(|seconds| Duration::seconds(*seconds))
+                .collect::<Vec<_>>(),
+        );
+        event.location = json.location;
+        event.recurrence = json.recurrence;
+        event.status = json.status;
+        event.color = json.color;
+        event.url = json.url;
+        event.set_id(json.id);
+        event.set_timestamps(json.created_at, json.updated_at);
+        event.validate()?;
+
+        Ok(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An event in the json representation, which must keep deserializing and serializing to exactly this
+    const EVENT_JSON: &str = r##"{
+  "schema_version": 1,
+  "id": 42,
+  "name": "Event Name",
+  "description": "Description",
+  "location": "Room 1",
+  "attendees": [
+    "alice@example.com"
+  ],
+  "start": "2022-04-04T10:00:00Z",
+  "end": "2022-04-04T11:00:00Z",
+  "all_day": false,
+  "timezone": "Europe/Berlin",
+  "recurrence": null,
+  "tags": [
+    "work"
+  ],
+  "priority": "High",
+  "status": "Scheduled",
+  "color": "#ff0000",
+  "reminders": [
+    900
+  ],
+  "created_at": "2022-04-01T08:00:00Z",
+  "updated_at": "2022-04-02T08:00:00Z",
+  "url": "https://example.com/call"
+}"##;
+
+    #[test]
+    fn round_trips_stable_json() {
+        let event = Event::from_json(EVENT_JSON).unwrap();
+        assert_eq!(event.id(), 42);
+        assert_eq!(event.tz, Tz::Europe__Berlin);
+        assert_eq!(event.reminders, vec![Duration::minutes(15)]);
+        assert_eq!(event.updated_at().to_rfc3339(), "2022-04-02T08:00:00+00:00");
+
+        assert_eq!(event.to_json().unwrap(), EVENT_JSON);
+        assert_eq!(Event::from_json(&event.to_json().unwrap()).unwrap(), event);
+
+        let newer = EVENT_JSON.replace("\"schema_version\": 1", "\"schema_version\": 2");
+        assert!(matches!(
+            Event::from_json(&newer),
+            Err(CalendarError::InvalidEvent(_))
+        ));
+        let invalid = EVENT_JSON.replace("Event Name", "");
+        assert!(Event::from_json(&invalid).is_err());
+        let invalid = EVENT_JSON.replace("https://example.com/call", "not a url");
+        assert!(Event::from_json(&invalid).is_err());
+    }
+}
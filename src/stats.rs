@@ -0,0 +1,153 @@
+//! Aggregate statistics about events
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::event::Event;
+
+/// A summary of a set of events (see [stats])
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    /// The total number of events
+    pub total: usize,
+    /// The number of events that happened before `now`
+    pub past: usize,
+    /// The number of events that happen at or after `now`
+    pub future: usize,
+    /// The earliest [Event::date_time] of all events, `None` if there are no events
+    pub earliest: Option<DateTime<Utc>>,
+    /// The latest [Event::date_time] of all events, `None` if there are no events
+    pub latest: Option<DateTime<Utc>>,
+    /// The number of events with each tag. Tags are trimmed and lowercased, since tags are compared ignoring case (see
+    /// [Event::has_tag])
+    pub tags: BTreeMap<String, usize>,
+}
+
+/// Computes a [Stats] summary of the events relative to `now`
+pub fn stats(events: &[Event], now: DateTime<Utc>) -> Stats {
+    let mut stats = Stats::default();
+
+    for event in events {
+        stats.total += 1;
+
+        if event.date_time < now {
+            stats.past += 1;
+        } else {
+            stats.future += 1;
+        }
+
+        let mut tags: Vec<String> = event
+            .tags
+            .iter()
+            .map(|tag| tag.trim().to_lowercase())
+            .collect();
+        // Don't count an event twice if it has the same tag multiple times
+        tags.sort();
+        tags.dedup();
+
+        for tag in tags {
+            *stats.tags.entry(tag).or_default() += 1;
+        }
+    }
+
+    stats.earliest = earliest(events).map(|event| event.date_time);
+    stats.latest = latest(events).map(|event| event.date_time);
+
+    stats
+}
+
+/// Returns the event with the earliest [Event::date_time], `None` if there are no events. If several events are at
+/// that time, the first of them is returned
+pub fn earliest(events: &[Event]) -> Option<&Event> {
+    events.iter().min_by_key(|event| event.date_time)
+}
+
+/// Returns the event with the latest [Event::date_time], `None` if there are no events. If several events are at that
+/// time, the first of them is returned
+pub fn latest(events: &[Event]) -> Option<&Event> {
+    events.iter().rev().max_by_key(|event| event.date_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::App;
+
+    #[test]
+    fn computes_stats() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let now = Utc.ymd(2022, 4, 4).and_hms(12, 0, 0);
+        let events = vec![
+            Event::new(
+                "Past",
+                None,
+                Utc.ymd(2022, 1, 1).and_hms(0, 0, 0),
+                None,
+                &app,
+            )
+            .unwrap()
+            .with_tags(&["Work", "work"]),
+            Event::new("Now", None, now, None, &app)
+                .unwrap()
+                .with_tags(&["health"]),
+            Event::new(
+                "Future",
+                None,
+                Utc.ymd(2023, 1, 1).and_hms(0, 0, 0),
+                None,
+                &app,
+            )
+            .unwrap()
+            .with_tags(&["work"]),
+        ];
+
+        let stats = stats(&events, now);
+
+        assert_eq!(stats.total, 3);
+        assert_eq!(stats.past, 1);
+        assert_eq!(stats.future, 2);
+        assert_eq!(stats.earliest, Some(Utc.ymd(2022, 1, 1).and_hms(0, 0, 0)));
+        assert_eq!(stats.latest, Some(Utc.ymd(2023, 1, 1).and_hms(0, 0, 0)));
+        assert_eq!(stats.tags.get("work"), Some(&2));
+        assert_eq!(stats.tags.get("health"), Some(&1));
+
+        assert_eq!(super::stats(&[], now), Stats::default());
+    }
+
+    #[test]
+    fn finds_earliest_and_latest_events() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = |name: &str, day: u32| {
+            Event::new(
+                name,
+                None,
+                Utc.ymd(2022, 4, day).and_hms(10, 0, 0),
+                None,
+                &app,
+            )
+            .unwrap()
+        };
+        let events = vec![
+            event("Middle", 5),
+            event("Last", 9),
+            event("First", 1),
+            event("Also first", 1),
+            event("Also last", 9),
+        ];
+
+        assert_eq!(earliest(&events), Some(&events[2]));
+        assert_eq!(latest(&events), Some(&events[1]));
+
+        assert_eq!(earliest(&[]), None);
+        assert_eq!(latest(&[]), None);
+    }
+}
@@ -0,0 +1,207 @@
+//! Templates for the kinds of events that are created again and again (like a weekly "1:1"), stored in
+//! [TEMPLATES_FILE] in [App::data_dir], so they are shared by all calendars
+
+use std::{collections::BTreeMap, fs, io};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{CalendarError, Context, Result},
+    event::{self, Event},
+    App,
+};
+
+/// The name of the file in [App::data_dir] that stores the templates
+pub const TEMPLATES_FILE: &str = "templates.json";
+
+/// The defaults for events created with [from_template]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Template {
+    /// The name of the template, which is also the name of the events created from it. Unique among all templates
+    pub name: String,
+    /// How long events created from the template last (in minutes), `None` for events without an end time
+    #[serde(default)]
+    pub duration: Option<u32>,
+    /// The description of events created from the template
+    #[serde(default)]
+    pub description: Option<String>,
+    /// The tags of events created from the template
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Template {
+    /// Creates a template without duration, description and tags
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            duration: None,
+            description: None,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Returns [Template::duration] as a duration
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+            .map(|minutes| Duration::minutes(minutes.into()))
+    }
+}
+
+/// Creates an event from the template with the given name, starting at `at` and ending after the template's duration.
+/// The event gets a new id, but isn't stored yet. Fails with [CalendarError::TemplateNotFound] if there is no such
+/// template
+pub fn from_template(app: &App, template_name: &str, at: DateTime<Utc>) -> Result<Event> {
+    let template = load_template(app, template_name)?;
+    let end_time = template.duration().map(|duration| at + duration);
+
+    Ok(Event::new(
+        &template.name,
+        template.description.as_deref(),
+        at,
+        end_time,
+        app,
+    )?
+    .with_tags(&template.tags))
+}
+
+/// Returns the template with the given name. Fails with [CalendarError::TemplateNotFound] if there is no such template
+pub fn load_template(app: &App, name: &str) -> Result<Template> {
+    read_templates(app)?
+        .remove(name)
+        .ok_or_else(|| CalendarError::TemplateNotFound(name.to_string()))
+}
+
+/// Returns all templates, sorted by name
+pub fn list_templates(app: &App) -> Result<Vec<Template>> {
+    Ok(read_templates(app)?.into_values().collect())
+}
+
+/// Stores the template, replacing the template with the same name if there is one. Fails if the name can't be used as
+/// the name of an event (see [event::validate_name])
+pub fn save_template(app: &App, template: &Template) -> Result<()> {
+    event::validate_name(&template.name)?;
+
+    let _lock = lock(app)?;
+    let mut templates = read_templates(app)?;
+    templates.insert(template.name.clone(), template.clone());
+
+    write_templates(app, &templates)
+}
+
+/// Deletes the template with the given name. Fails with [CalendarError::TemplateNotFound] if there is no such
+/// template
+pub fn delete_template(app: &App, name: &str) -> Result<()> {
+    let _lock = lock(app)?;
+    let mut templates = read_templates(app)?;
+
+    if templates.remove(name).is_none() {
+        return Err(CalendarError::TemplateNotFound(name.to_string()));
+    }
+
+    write_templates(app, &templates)
+}
+
+/// Reads all templates from [TEMPLATES_FILE], keyed by name. There are no templates if the file doesn't exist
+fn read_templates(app: &App) -> Result<BTreeMap<String, Template>> {
+    let path = app.data_dir.join(TEMPLATES_FILE);
+
+    let templates: Vec<Template> = match fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).with_context(|| {
+            format!(
+                "failed to deserialize templates from `{}`",
+                path.to_string_lossy()
+            )
+        })?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("failed to read from file `{}`", path.to_string_lossy()))
+        }
+    };
+
+    Ok(templates
+        .into_iter()
+        .map(|template| (template.name.clone(), template))
+        .collect())
+}
+
+/// Locks the events directory of the default calendar (see [event::lock]), since the templates are shared by all
+/// calendars
+fn lock(app: &App) -> Result<fs::File> {
+    let mut default = App::new(&app.data_dir);
+    default.read_only = app.read_only;
+
+    event::lock(&default)
+}
+
+/// Writes the templates to [TEMPLATES_FILE] (as a json array sorted by name), the templates have to be locked (see
+/// [lock])
+fn write_templates(app: &App, templates: &BTreeMap<String, Template>) -> Result<()> {
+    let templates: Vec<&Template> = templates.values().collect();
+    let json =
+        serde_json::to_vec_pretty(&templates).with_context(|| "failed to serialize templates")?;
+
+    event::write_atomic(&app.data_dir.join(TEMPLATES_FILE), &json)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn creates_events_from_templates() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        assert!(list_templates(&app).unwrap().is_empty());
+
+        let gym = Template {
+            duration: Some(90),
+            tags: vec!["health".to_string()],
+            ..Template::new("Gym")
+        };
+        let one_on_one = Template {
+            description: Some("Weekly check-in".to_string()),
+            ..Template::new("1:1")
+        };
+        save_template(&app, &gym).unwrap();
+        save_template(&app, &one_on_one).unwrap();
+        assert!(save_template(&app, &Template::new("")).is_err());
+        assert_eq!(list_templates(&app).unwrap(), vec![one_on_one, gym.clone()]);
+
+        let at = Utc.ymd(2022, 4, 4).and_hms(18, 0, 0);
+        let event = from_template(&app, "Gym", at).unwrap();
+        assert_eq!(event.name, "Gym");
+        assert_eq!(event.end_time, Some(Utc.ymd(2022, 4, 4).and_hms(19, 30, 0)));
+        assert_eq!(event.tags, vec!["health"]);
+
+        let event = from_template(&app, "1:1", at).unwrap();
+        assert_eq!(event.description.as_deref(), Some("Weekly check-in"));
+        assert_eq!(event.end_time, None);
+
+        // Templates are shared by all calendars
+        let work = app.calendar("work").unwrap();
+        crate::utils::create_dirs(&work).unwrap();
+        let updated = Template {
+            duration: Some(60),
+            ..gym
+        };
+        save_template(&work, &updated).unwrap();
+        assert_eq!(load_template(&app, "Gym").unwrap(), updated);
+
+        delete_template(&app, "Gym").unwrap();
+        assert!(matches!(
+            from_template(&app, "Gym", at),
+            Err(CalendarError::TemplateNotFound(_))
+        ));
+        assert!(matches!(
+            delete_template(&app, "Gym"),
+            Err(CalendarError::TemplateNotFound(_))
+        ));
+    }
+}
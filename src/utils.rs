@@ -1,28 +1,102 @@
 //! Utility functions for common tasks
 
-use std::{fs, io};
+use std::{env, fs, io};
 
-use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, LocalResult, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
 
-use crate::App;
+use crate::{
+    error::{CalendarError, Result},
+    App,
+};
 
-/// Creates all directories necessary for the program to run (if they don't exist)
+/// Creates all directories necessary for the program to run (if they don't exist). Fails with
+/// [CalendarError::InvalidDataDir] if one of them exists but isn't a directory, or can't be created because of missing
+/// permissions, a read-only filesystem or a parent directory that can't be created. Other failures are
+/// [CalendarError::Io] errors
 pub fn create_dirs(app: &App) -> Result<()> {
     // We don't need to create App::data_dir because fs::create_dir_all() will create it for us, as App::events_dir is a subdirectory of App::data_dir
     if let Err(e) = fs::create_dir_all(&app.events_dir) {
         // Don't return Err if the directory already exists (that is expected)
         if e.kind() != io::ErrorKind::AlreadyExists {
-            return Err(e).with_context(|| {
-                format!(
-                    "failed to create directory `{}`",
-                    app.events_dir.to_string_lossy()
-                )
-            });
+            return Err(creation_error(app, e));
         }
     }
+
+    // fs::create_dir_all() also reports AlreadyExists if there is a file at the path
+    if !app.events_dir.is_dir() {
+        return Err(CalendarError::InvalidDataDir(format!(
+            "`{}` exists but is not a directory",
+            app.events_dir.to_string_lossy()
+        )));
+    }
+
     Ok(())
 }
 
+/// Explains why [App::events_dir] couldn't be created (see [create_dirs])
+fn creation_error(app: &App, e: io::Error) -> CalendarError {
+    let dir = app.events_dir.to_string_lossy();
+
+    match e.kind() {
+        io::ErrorKind::PermissionDenied => CalendarError::InvalidDataDir(format!(
+            "permission denied while creating `{}`, make sure `{}` is writable (or open the calendar read-only)",
+            dir,
+            app.data_dir.to_string_lossy()
+        )),
+        io::ErrorKind::ReadOnlyFilesystem => CalendarError::InvalidDataDir(format!(
+            "`{}` can't be created on a read-only filesystem (open the calendar read-only instead)",
+            dir
+        )),
+        io::ErrorKind::NotFound => CalendarError::InvalidDataDir(format!(
+            "`{}` can't be created because one of its parent directories can't be created",
+            dir
+        )),
+        _ => CalendarError::Io {
+            context: format!("failed to create directory `{}`", dir),
+            source: e,
+        },
+    }
+}
+
+/// Returns the timezone of the system: the one in the `TZ` environment variable if it's set to the name of a timezone
+/// (like `Europe/Berlin`), otherwise the one `/etc/localtime` links to. `None` if neither names a known timezone
+pub fn system_timezone() -> Option<Tz> {
+    if let Some(tz) = env::var("TZ")
+        .ok()
+        .and_then(|tz| tz.trim_start_matches(':').parse().ok())
+    {
+        return Some(tz);
+    }
+
+    let target = fs::read_link("/etc/localtime").ok()?;
+
+    target.to_str()?.split_once("zoneinfo/")?.1.parse().ok()
+}
+
+/// Converts a local (wall-clock) time in the timezone `tz` to UTC. Ambiguous local times (that occur twice because the
+/// clocks were turned back) resolve to the earlier point in time, local times that don't exist (because the clocks were
+/// turned forward) are shifted forward by the length of the gap, like the clocks were
+pub fn local_to_utc<T: TimeZone>(tz: &T, local: NaiveDateTime) -> DateTime<Utc> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(date_time) | LocalResult::Ambiguous(date_time, _) => {
+            date_time.with_timezone(&Utc)
+        }
+        LocalResult::None => {
+            // Find the last local time before the gap and apply its offset (gaps are usually an hour long)
+            let mut before = local;
+
+            loop {
+                before -= Duration::minutes(30);
+
+                if let Some(date_time) = tz.from_local_datetime(&before).earliest() {
+                    return date_time.with_timezone(&Utc) + (local - before);
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -37,4 +111,62 @@ mod tests {
         assert!(app.data_dir.exists());
         assert!(app.events_dir.exists());
     }
+
+    #[test]
+    fn rejects_events_dir_that_is_a_file() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        fs::write(app.data_dir.join("events"), "").unwrap();
+
+        let error = create_dirs(&app).unwrap_err();
+        assert!(matches!(error, CalendarError::InvalidDataDir(_)));
+        assert!(error.to_string().contains("exists but is not a directory"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn explains_why_dirs_cant_be_created() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_dir.path().join("evnt"));
+
+        let error = creation_error(&app, io::ErrorKind::NotFound.into());
+        assert!(matches!(error, CalendarError::InvalidDataDir(_)));
+        assert!(error
+            .to_string()
+            .contains("one of its parent directories can't be created"));
+
+        fs::set_permissions(temp_dir.path(), fs::Permissions::from_mode(0o555)).unwrap();
+        // Root can create directories in read-only directories anyway, there is nothing to check then
+        let enforced = fs::create_dir(temp_dir.path().join("probe")).is_err();
+        let result = create_dirs(&app);
+        fs::set_permissions(temp_dir.path(), fs::Permissions::from_mode(0o755)).unwrap();
+
+        if enforced {
+            let error = result.unwrap_err();
+            assert!(matches!(error, CalendarError::InvalidDataDir(_)));
+            assert!(error
+                .to_string()
+                .contains("permission denied while creating"));
+        }
+    }
+
+    #[test]
+    fn converts_local_times_in_dst_gaps() {
+        use chrono_tz::Europe::Berlin;
+
+        // The clocks in Berlin were turned forward from 02:00 to 03:00 on 2022-03-27
+        let in_gap = chrono::NaiveDate::from_ymd(2022, 3, 27).and_hms(2, 30, 0);
+        assert_eq!(
+            local_to_utc(&Berlin, in_gap),
+            Utc.ymd(2022, 3, 27).and_hms(1, 30, 0)
+        );
+
+        let before_gap = chrono::NaiveDate::from_ymd(2022, 3, 27).and_hms(1, 30, 0);
+        assert_eq!(
+            local_to_utc(&Berlin, before_gap),
+            Utc.ymd(2022, 3, 27).and_hms(0, 30, 0)
+        );
+    }
 }
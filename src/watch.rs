@@ -0,0 +1,196 @@
+//! Watching [App::events_dir] for changes made by other processes (e.g. another instance of the program or a sync
+//! tool)
+
+use std::{collections::HashSet, path::Path, sync::mpsc};
+
+use notify::{
+    event::{ModifyKind, RenameMode},
+    EventKind, RecursiveMode, Watcher,
+};
+
+use crate::{
+    error::{Context, Result},
+    event, App,
+};
+
+/// A change to an event file in [App::events_dir], with the id of the event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsEvent {
+    /// A file for an event that wasn't stored before was created
+    Created(u128),
+    /// The file of an already stored event was changed or replaced
+    Modified(u128),
+    /// The file of an event was deleted (converting a legacy file to the codec doesn't count as deleting it)
+    Deleted(u128),
+}
+
+/// Watches [App::events_dir] and its month directories (see [event::Layout::Monthly]) and calls `callback` for every
+/// change to an event file (see [FsEvent]). Files that aren't named like events (see [event::event_id]), like
+/// temporary files, and files in other directories (like named calendars) are ignored. Blocks until watching fails
+pub fn watch(app: &App, mut callback: impl FnMut(FsEvent)) -> Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(sender).with_context(|| "failed to create a file watcher")?;
+    watcher
+        .watch(&app.events_dir, RecursiveMode::Recursive)
+        .with_context(|| {
+            format!(
+                "failed to watch directory `{}`",
+                app.events_dir.to_string_lossy()
+            )
+        })?;
+
+    let mut known_ids: HashSet<u128> = event::get_ids(app)?.into_iter().collect();
+
+    for result in receiver {
+        let notify_event = result.with_context(|| {
+            format!(
+                "failed to watch directory `{}`",
+                app.events_dir.to_string_lossy()
+            )
+        })?;
+
+        for fs_event in fs_events(app, &notify_event, &mut known_ids) {
+            callback(fs_event);
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts a change reported by the watcher into changes to event files. `known_ids` are the ids of the events that
+/// are currently stored and gets updated with the change
+fn fs_events(
+    app: &App,
+    notify_event: &notify::Event,
+    known_ids: &mut HashSet<u128>,
+) -> Vec<FsEvent> {
+    let event_id = |path: &Path| {
+        event::is_in_events_dir(app, path)
+            .then(|| event::event_id(path))
+            .flatten()
+    };
+    let changed = |path: &Path, known_ids: &mut HashSet<u128>| {
+        event_id(path).map(|id| {
+            if known_ids.insert(id) {
+                FsEvent::Created(id)
+            } else {
+                FsEvent::Modified(id)
+            }
+        })
+    };
+    let removed = |path: &Path, known_ids: &mut HashSet<u128>| match event_id(path) {
+        // The event might still be stored in a file with another extension
+        Some(id) if !event::event_exists(app, id) && known_ids.remove(&id) => {
+            Some(FsEvent::Deleted(id))
+        }
+        _ => None,
+    };
+
+    match notify_event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            match notify_event.paths.as_slice() {
+                [from, to] => {
+                    let deleted = removed(from, known_ids);
+                    changed(to, known_ids).into_iter().chain(deleted).collect()
+                }
+                _ => Vec::new(),
+            }
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) | EventKind::Remove(_) => {
+            notify_event
+                .paths
+                .iter()
+                .filter_map(|path| removed(path, known_ids))
+                .collect()
+        }
+        EventKind::Modify(ModifyKind::Metadata(_)) | EventKind::Access(_) => Vec::new(),
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Any | EventKind::Other => {
+            notify_event
+                .paths
+                .iter()
+                .filter_map(|path| changed(path, known_ids))
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::{TimeZone, Utc};
+    use notify::event::{CreateKind, DataChange, RemoveKind};
+
+    use super::*;
+    use crate::event::Event;
+
+    #[test]
+    fn converts_watcher_events() {
+        let temp_data_dir = assert_fs::TempDir::new().unwrap();
+        let app = App::new(temp_data_dir.path());
+        crate::utils::create_dirs(&app).unwrap();
+
+        let event = Event::new(
+            "Event Name",
+            None,
+            Utc.ymd(2022, 4, 4).and_hms(10, 0, 0),
+            None,
+            &app,
+        )
+        .unwrap();
+        let path = event::event_path(&app, event.id());
+        let temp_path = app.events_dir.join(".0.json.tmp");
+        let mut known_ids = HashSet::new();
+
+        let notify_event = |kind, paths: &[&Path]| {
+            paths.iter().fold(notify::Event::new(kind), |e, path| {
+                e.add_path(path.to_path_buf())
+            })
+        };
+
+        // Writing an event creates a temporary file that is renamed to the event file
+        assert!(fs_events(
+            &app,
+            &notify_event(EventKind::Create(CreateKind::File), &[&temp_path]),
+            &mut known_ids
+        )
+        .is_empty());
+        assert_eq!(
+            fs_events(
+                &app,
+                &notify_event(
+                    EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+                    &[&temp_path, &path]
+                ),
+                &mut known_ids
+            ),
+            vec![FsEvent::Created(event.id())]
+        );
+        assert_eq!(
+            fs_events(
+                &app,
+                &notify_event(
+                    EventKind::Modify(ModifyKind::Data(DataChange::Any)),
+                    &[&path]
+                ),
+                &mut known_ids
+            ),
+            vec![FsEvent::Modified(event.id())]
+        );
+
+        // Removing a file only deletes the event if it isn't stored in another file
+        event.store(&app).unwrap();
+        let legacy_path = event::legacy_event_path(&app, event.id());
+        let remove = notify_event(EventKind::Remove(RemoveKind::File), &[&legacy_path]);
+        assert!(fs_events(&app, &remove, &mut known_ids).is_empty());
+
+        fs::remove_file(&path).unwrap();
+        let remove = notify_event(EventKind::Remove(RemoveKind::File), &[&path]);
+        assert_eq!(
+            fs_events(&app, &remove, &mut known_ids),
+            vec![FsEvent::Deleted(event.id())]
+        );
+        assert!(known_ids.is_empty());
+    }
+}